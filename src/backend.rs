@@ -0,0 +1,931 @@
+use crate::mpv_ipc::{IpcEvent, MpvIpc};
+use crate::settings::BackendKind;
+use crate::{Track, config};
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+#[cfg(target_os = "windows")]
+use std::os::windows::process::CommandExt;
+
+// -------------------------------------------------------------------
+// the thing that actually makes sound. player.rs only talks to this
+// trait, so mpv can be swapped for the null backend (no audio device,
+// no mpv binary) to run whytui headless on servers and in CI
+// -------------------------------------------------------------------
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PlayerEvent {
+    // the track stopped on its own, error is set if the player gave up on it.
+    // position is where it got to, (position, duration) as position() said
+    // last while it still could
+    Ended {
+        error: Option<String>,
+        position: Option<(f64, f64)>,
+    },
+    // the track ended and the preloaded one took over without a gap
+    Advanced { source: String },
+}
+
+pub trait PlayerBackend: Send + Sync {
+    fn load(&self, source: &str, track: &Track, start: Option<f64>, volume: i64)
+    -> Result<(), Box<dyn std::error::Error>>;
+    fn toggle_pause(&self);
+    fn is_paused(&self) -> bool;
+    fn seek(&self, seconds: i64);
+    fn set_volume(&self, volume: i64);
+    // playback rate, pitch stays put. position() keeps reporting track time
+    fn set_speed(&self, speed: f64);
+    // (position, duration) in seconds, None when nothing is loaded
+    fn position(&self) -> Option<(f64, f64)>;
+    fn poll_event(&self) -> Option<PlayerEvent>;
+    // what to switch to when the current track ends, None clears it
+    fn preload(&self, next: Option<(&str, &Track)>);
+    fn stop(&self);
+    // labelled audio filter in mpv's --af syntax ("lavfi=[...]" for ffmpeg
+    // graphs, "rubberband=..."), None removes it. filters stay across tracks
+    fn set_filter(&self, _label: &str, _filter: Option<&str>) {}
+    // (name, description) of the outputs the player can use, empty if it can't tell
+    fn audio_devices(&self) -> Vec<(String, String)> {
+        Vec::new()
+    }
+    // "auto" = the system default. a picked device that goes away is
+    // swapped for the default until it comes back
+    fn set_audio_device(&self, _name: &str) {}
+    // stop and release the player for good (quitting)
+    fn shutdown(&self) {
+        self.stop();
+    }
+}
+
+static BACKEND: OnceLock<Box<dyn PlayerBackend>> = OnceLock::new();
+
+pub fn backend() -> &'static dyn PlayerBackend {
+    BACKEND
+        .get_or_init(|| match config().playback.backend {
+            BackendKind::Mpv => Box::new(MpvBackend::default()),
+            BackendKind::Null => Box::new(NullBackend::new(NULL_TICK * config().playback.null_speed)),
+        })
+        .as_ref()
+}
+
+// ---- MPV ----
+// one idle mpv for the whole session, driven over a single persistent ipc
+// connection (mpv_ipc.rs). the playing file is always playlist entry 0,
+// the preloaded one entry 1. crossfades and faded skips retire that mpv:
+// it fades out on a thread of its own while a fresh one takes over. the
+// one a crossfade hands over to is started early, as soon as the preload
+// asks for a crossfade, so the switch never waits on mpv coming up
+
+#[derive(Default)]
+pub struct MpvBackend {
+    state: Mutex<MpvState>,
+    // label -> af filter, outlives mpv restarts
+    filters: Mutex<BTreeMap<String, String>>,
+    // retired mpvs still fading out, killed when done or on shutdown
+    retired: Arc<Mutex<Vec<Child>>>,
+    // bumped by every pause fade, crossfade and load: a running ramp stops
+    // as soon as a newer one takes over
+    fades: Arc<AtomicU64>,
+    // the connected mpv the next crossfade switches to
+    spare: Arc<Mutex<Spare>>,
+}
+
+#[derive(Default)]
+struct Spare {
+    ready: Option<(Child, MpvIpc)>,
+    spawning: bool,
+    // filters or speed changed while it was coming up, it is killed on arrival
+    stale: bool,
+    // shut down, nothing may be left running
+    closed: bool,
+}
+
+#[derive(Default)]
+struct MpvState {
+    child: Option<Child>,
+    ipc: Option<Arc<MpvIpc>>,
+    loaded: bool,
+    // where pause is heading, set before a fade starts: mpv itself stays
+    // unpaused until a fade out is done
+    paused: bool,
+    preloaded: Option<String>,
+    // the preload waits for a crossfade instead of sitting in the playlist
+    crossfade: bool,
+    volume: i64,
+    album: String,
+    next_album: String,
+    // the picked output ("" or "auto" = default) and what mpv is set to now
+    device: String,
+    routed: Option<String>,
+}
+
+static SPAWNED: AtomicU32 = AtomicU32::new(0);
+
+// a new path for every mpv, a fading one still owns the previous path
+pub fn next_ipc_path() -> String {
+    crate::instance::mpv_socket(SPAWNED.fetch_add(1, Ordering::Relaxed))
+}
+
+fn fade_duration() -> Duration {
+    Duration::from_millis(config().playback.fade_ms)
+}
+
+// volume steps over ipc, about 40 a second. gives up (false) once `current` says
+// a newer fade took over
+fn ramp(ipc: &MpvIpc, from: i64, to: i64, over: Duration, current: impl Fn() -> bool) -> bool {
+    let steps = (over.as_millis() / 25).max(1) as i64;
+    let pause = over / steps as u32;
+    for i in 1..=steps {
+        if !current() {
+            return false;
+        }
+        ipc.send(json!(["set_property", "volume", from + (to - from) * i / steps]));
+        std::thread::sleep(pause);
+    }
+    current()
+}
+
+// placeholders from api.rs / offline.rs don't make two songs an album
+fn same_album(a: &str, b: &str) -> bool {
+    a == b && !a.is_empty() && a != "Unknown" && a != "Offline Library"
+}
+
+// inside the crossfade window of a track long enough to have one.
+// the window is wall clock time, so it covers more of the track when sped up
+fn crossfade_due(ipc: &MpvIpc) -> bool {
+    let secs = config().playback.crossfade_secs * crate::tempo::speed();
+    let props = ipc.props();
+    match (props.time_pos, props.duration) {
+        (Some(pos), Some(duration)) if duration > secs * 2.0 => {
+            !props.pause && duration - pos <= secs
+        }
+        _ => false,
+    }
+}
+
+// the picked device while it's plugged in, the system default while it's not
+fn route_device(st: &mut MpvState, ipc: &MpvIpc, devices: &[(String, String)]) {
+    let wanted = !st.device.is_empty() && st.device != "auto";
+    let present = devices.iter().any(|(name, _)| *name == st.device);
+    let target = if wanted && present { st.device.as_str() } else { "auto" };
+    if st.routed.as_deref() == Some(target) {
+        return;
+    }
+    if let Err(e) = ipc.command(json!(["set_property", "audio-device", target])) {
+        crate::ui_common::set_status_line(Some(format!(":( Audio device failed: {}", e)));
+        return;
+    }
+    if wanted && !present {
+        crate::ui_common::set_status_line(Some(":( Audio device gone, using default".to_string()));
+    } else if wanted && st.routed.as_deref() == Some("auto") {
+        crate::ui_common::set_status_line(Some("AUDIO DEVICE IS BACK".to_string()));
+    }
+    st.routed = Some(target.to_string());
+}
+
+fn kill_child(child: &mut Child) {
+    #[cfg(target_os = "windows")]
+    {
+        let _ = Command::new("taskkill")
+            .args(["/F", "/T", "/PID", &child.id().to_string()])
+            .creation_flags(0x08000000)
+            .output();
+    }
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+fn af_entry(label: &str, filter: &str) -> String {
+    format!("@{}:{}", label, filter)
+}
+
+fn spawn_mpv(volume: i64, af: &[String]) -> Result<(Child, MpvIpc), Box<dyn std::error::Error>> {
+    let ipc = next_ipc_path();
+
+    #[cfg(unix)]
+    let _ = std::fs::remove_file(&ipc);
+
+    let mut cmd = Command::new("mpv");
+    cmd.arg("--no-video")
+        .arg("--really-quiet")
+        .arg("--force-window=no")
+        .arg("--idle=yes")
+        .arg("--gapless-audio=yes")
+        .arg("--prefetch-playlist=yes")
+        .arg(format!("--input-ipc-server={}", ipc))
+        .arg(format!("--volume={}", volume))
+        .arg(format!("--replaygain={}", crate::loudness::replaygain_option()))
+        .arg(format!("--speed={}", crate::tempo::speed()))
+        .arg("--demuxer-lavf-o=protocol_whitelist=[file,http,https,tcp,tls,crypto,data]")
+        .arg("--http-header-fields=Referer: https://music.youtube.com/,Origin: https://music.youtube.com")
+        // user agent, cookies, proxy and [network] mpv_args
+        .args(crate::net::mpv_args())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+
+    // added to whatever af mpv.conf sets, not replacing it
+    for filter in af {
+        cmd.arg(format!("--af-append={}", filter));
+    }
+
+    let mut child = cmd.spawn()?;
+
+    // wait for the ipc socket, loadfile before that would be lost
+    for _ in 0..50 {
+        if let Ok(conn) = MpvIpc::connect(&ipc) {
+            return Ok((child, conn));
+        }
+        if let Ok(Some(status)) = child.try_wait() {
+            return Err(format!("mpv exited with {}", status).into());
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    kill_child(&mut child);
+    Err("mpv did not open its ipc socket".into())
+}
+
+fn loadfile(ipc: &MpvIpc, url: &str, flags: &str, start: Option<f64>) -> Result<(), String> {
+    let mut cmd = json!({"name": "loadfile", "url": url, "flags": flags});
+    let mut options = Vec::new();
+    if let Some(secs) = start {
+        options.push(format!("start={:.1}", secs));
+    }
+    // per file, the mpv itself keeps the configured one for everything else.
+    // %len% quotes it, user agents are full of characters mpv would split on
+    if let Some(ua) = crate::resolve::user_agent_for(url) {
+        options.push(format!("user-agent=%{}%{}", ua.len(), ua));
+    }
+    if !options.is_empty() {
+        cmd["options"] = json!(options.join(","));
+    }
+    ipc.command(cmd).map(|_| ())
+}
+
+impl MpvBackend {
+    fn ipc(&self) -> Option<Arc<MpvIpc>> {
+        self.state.lock().unwrap().ipc.clone()
+    }
+
+    // a new id for a fade (or a load) that replaces whatever ramp is running
+    fn next_fade(&self) -> u64 {
+        self.fades.fetch_add(1, Ordering::SeqCst) + 1
+    }
+
+    fn is_current(fades: &AtomicU64, id: u64) -> bool {
+        fades.load(Ordering::SeqCst) == id
+    }
+
+    fn af(&self) -> Vec<String> {
+        self.filters
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(label, filter)| af_entry(label, filter))
+            .collect()
+    }
+
+    // a fresh mpv with the current filters, takes up to a few seconds
+    fn spawn(&self, volume: i64) -> Result<(Child, MpvIpc), Box<dyn std::error::Error>> {
+        spawn_mpv(volume, &self.af())
+    }
+
+    // brings up a silent spare mpv on a thread unless one is ready or on its way
+    fn warm_spare(&self) {
+        // MpvIpc::connect starts its reader on the runtime
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        {
+            let mut spare = self.spare.lock().unwrap();
+            if spare.ready.is_some() || spare.spawning || spare.closed {
+                return;
+            }
+            spare.spawning = true;
+            spare.stale = false;
+        }
+        let af = self.af();
+        let slot = self.spare.clone();
+        std::thread::spawn(move || {
+            let _runtime = runtime.enter();
+            let spawned = spawn_mpv(0, &af);
+            let mut spare = slot.lock().unwrap();
+            spare.spawning = false;
+            match spawned {
+                Ok((mut child, _)) if spare.stale || spare.closed => kill_child(&mut child),
+                Ok(ready) => spare.ready = Some(ready),
+                Err(_) => {}
+            }
+        });
+    }
+
+    // the spare if it is up and still alive
+    fn take_spare(&self) -> Option<(Child, MpvIpc)> {
+        let (mut child, ipc) = self.spare.lock().unwrap().ready.take()?;
+        if matches!(child.try_wait(), Ok(None)) && ipc.is_alive() {
+            return Some((child, ipc));
+        }
+        kill_child(&mut child);
+        None
+    }
+
+    // the spare was started with old settings, the next crossfade gets a new one
+    fn drop_spare(&self) {
+        let mut spare = self.spare.lock().unwrap();
+        spare.stale = spare.spawning;
+        if let Some((mut child, _)) = spare.ready.take() {
+            kill_child(&mut child);
+        }
+    }
+
+    fn install(&self, st: &mut MpvState, child: Child, ipc: MpvIpc) {
+        if let Some(mut old) = st.child.take() {
+            kill_child(&mut old);
+        }
+        // the device list comes from the new mpv, so route before loading anything
+        st.routed = None;
+        if let Ok(list) = ipc.command(json!(["get_property", "audio-device-list"]))
+            && let Some(devices) = crate::mpv_ipc::parse_devices(&list)
+        {
+            route_device(st, &ipc, &devices);
+        }
+        st.child = Some(child);
+        st.ipc = Some(Arc::new(ipc));
+        st.loaded = false;
+        st.paused = false;
+        st.preloaded = None;
+        st.crossfade = false;
+    }
+
+    fn ensure_running(
+        &self,
+        st: &mut MpvState,
+        volume: i64,
+    ) -> Result<Arc<MpvIpc>, Box<dyn std::error::Error>> {
+        let alive = st
+            .child
+            .as_mut()
+            .is_some_and(|c| matches!(c.try_wait(), Ok(None)))
+            && st.ipc.as_ref().is_some_and(|i| i.is_alive());
+        if !alive {
+            if let Some(mut old) = st.child.take() {
+                kill_child(&mut old);
+            }
+            let (child, ipc) = self.spawn(volume)?;
+            self.install(st, child, ipc);
+        }
+        Ok(st.ipc.clone().unwrap())
+    }
+
+    // moves the playing mpv aside, it fades out over `over` and is killed
+    fn retire(&self, st: &mut MpvState, over: Duration) {
+        let child = st.child.take();
+        let ipc = st.ipc.take();
+        st.loaded = false;
+        st.preloaded = None;
+        st.crossfade = false;
+        let Some(mut child) = child else {
+            return;
+        };
+        let Some(ipc) = ipc.filter(|i| i.is_alive()) else {
+            kill_child(&mut child);
+            return;
+        };
+
+        let pid = child.id();
+        let volume = st.volume;
+        self.retired.lock().unwrap().push(child);
+        let retired = self.retired.clone();
+        std::thread::spawn(move || {
+            ramp(&ipc, volume, 0, over, || true);
+            // quit lets mpv remove its socket, kill makes sure it's gone
+            ipc.send(json!(["quit"]));
+            std::thread::sleep(Duration::from_millis(200));
+            let mut list = retired.lock().unwrap();
+            if let Some(i) = list.iter().position(|c| c.id() == pid) {
+                kill_child(&mut list.remove(i));
+            }
+        });
+    }
+
+    // a fresh mpv that fades `source` in from silence
+    fn fade_in(
+        &self,
+        st: &mut MpvState,
+        source: &str,
+        over: Duration,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let ipc = self.ensure_running(st, 0)?;
+        loadfile(&ipc, source, "replace", None)?;
+        st.loaded = true;
+        let volume = st.volume;
+        let (fades, id) = (self.fades.clone(), self.next_fade());
+        std::thread::spawn(move || ramp(&ipc, 0, volume, over, || Self::is_current(&fades, id)));
+        Ok(())
+    }
+}
+
+impl PlayerBackend for MpvBackend {
+    fn load(
+        &self,
+        source: &str,
+        track: &Track,
+        start: Option<f64>,
+        volume: i64,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut st = self.state.lock().unwrap();
+        let ipc = self.ensure_running(&mut st, volume)?;
+
+        // a pause fade still running would pause or mute the new track
+        self.next_fade();
+        st.paused = false;
+        // pause and volume outlive the file in a long-lived mpv
+        ipc.send(json!(["set_property", "pause", false]));
+        ipc.send(json!(["set_property", "volume", volume]));
+        loadfile(&ipc, source, "replace", start)?;
+
+        st.loaded = true;
+        st.preloaded = None;
+        st.crossfade = false;
+        st.volume = volume;
+        st.album = track.album.clone();
+        Ok(())
+    }
+
+    fn toggle_pause(&self) {
+        // decided under the lock: a second press mid fade reverses the first
+        let (ipc, volume, pause, id) = {
+            let mut st = self.state.lock().unwrap();
+            let Some(ipc) = st.ipc.clone() else {
+                return;
+            };
+            st.paused = !st.paused;
+            (ipc, st.volume, st.paused, self.next_fade())
+        };
+        let fade = fade_duration();
+        if fade.is_zero() {
+            ipc.send(json!(["set_property", "pause", pause]));
+            return;
+        }
+        let fades = self.fades.clone();
+        std::thread::spawn(move || {
+            let current = || Self::is_current(&fades, id);
+            // a reversed fade carries on from wherever the last one got to
+            let level = ipc.props().volume.map_or(volume, |v| v as i64);
+            if pause {
+                if ramp(&ipc, level, 0, fade, current) {
+                    ipc.send(json!(["set_property", "pause", true]));
+                    ipc.send(json!(["set_property", "volume", volume]));
+                }
+            } else if ipc.props().pause {
+                ipc.send(json!(["set_property", "volume", 0]));
+                ipc.send(json!(["set_property", "pause", false]));
+                ramp(&ipc, 0, volume, fade, current);
+            } else {
+                ramp(&ipc, level, volume, fade, current);
+            }
+        });
+    }
+
+    fn is_paused(&self) -> bool {
+        let st = self.state.lock().unwrap();
+        st.ipc.is_some() && st.paused
+    }
+
+    fn seek(&self, seconds: i64) {
+        if let Some(ipc) = self.ipc() {
+            ipc.send(json!(["seek", seconds, "relative"]));
+        }
+    }
+
+    fn set_volume(&self, volume: i64) {
+        let mut st = self.state.lock().unwrap();
+        st.volume = volume;
+        if let Some(ipc) = &st.ipc {
+            ipc.send(json!(["set_property", "volume", volume]));
+        }
+    }
+
+    fn set_speed(&self, speed: f64) {
+        self.drop_spare();
+        if let Some(ipc) = self.ipc() {
+            ipc.send(json!(["set_property", "speed", speed]));
+        }
+    }
+
+    // served from observed properties, no round trip to mpv
+    fn position(&self) -> Option<(f64, f64)> {
+        let props = self.ipc()?.props();
+        Some((props.time_pos?, props.duration?))
+    }
+
+    fn poll_event(&self) -> Option<PlayerEvent> {
+        let mut st = self.state.lock().unwrap();
+        let ipc = st.ipc.clone()?;
+
+        // headphones unplugged, hdmi gone... mpv keeps the list up to date
+        if let Some(devices) = ipc.props().devices {
+            route_device(&mut st, &ipc, &devices);
+        }
+
+        if st.crossfade && st.loaded {
+            if crossfade_due(&ipc)
+                && let Some((child, next)) = self.take_spare()
+            {
+                let source = st.preloaded.clone().unwrap_or_default();
+                let album = std::mem::take(&mut st.next_album);
+                let over = Duration::from_secs_f64(config().playback.crossfade_secs);
+                self.retire(&mut st, over);
+                self.install(&mut st, child, next);
+                return Some(match self.fade_in(&mut st, &source, over) {
+                    Ok(()) => {
+                        st.album = album;
+                        PlayerEvent::Advanced { source }
+                    }
+                    Err(e) => PlayerEvent::Ended {
+                        error: Some(e.to_string()),
+                        position: None,
+                    },
+                });
+            }
+            // no spare yet (or it died): the crossfade starts once one is up
+            self.warm_spare();
+        }
+
+        let event = match ipc.next_event() {
+            Some(e) => e,
+            None => {
+                // a killed mpv may take a moment to close the socket
+                let status = st.child.as_mut()?.try_wait().ok()??;
+                if !st.loaded {
+                    return None;
+                }
+                st.loaded = false;
+                return Some(PlayerEvent::Ended {
+                    error: Some(format!("mpv exited with {}", status)),
+                    position: ipc.last_position(),
+                });
+            }
+        };
+        match event {
+            IpcEvent::NextStarted => {
+                // drop the finished entry 0 so the new track is entry 0 again
+                ipc.send(json!(["playlist-remove", 0]));
+                st.album = std::mem::take(&mut st.next_album);
+                let source = st.preloaded.take().unwrap_or_default();
+                Some(PlayerEvent::Advanced { source })
+            }
+            IpcEvent::Ended { error, position } if st.loaded => {
+                st.loaded = false;
+                st.preloaded = None;
+                Some(PlayerEvent::Ended { error, position })
+            }
+            IpcEvent::Closed if st.loaded => {
+                st.loaded = false;
+                st.preloaded = None;
+                Some(PlayerEvent::Ended {
+                    error: Some("mpv quit".to_string()),
+                    position: ipc.last_position(),
+                })
+            }
+            _ => None,
+        }
+    }
+
+    fn preload(&self, next: Option<(&str, &Track)>) {
+        let mut st = self.state.lock().unwrap();
+        let want = next.map(|(source, _)| source.to_string());
+        if !st.loaded || st.preloaded == want {
+            return;
+        }
+        let Some(ipc) = st.ipc.clone() else {
+            return;
+        };
+        // songs of one album run into each other on purpose, keep those gapless
+        let crossfade = config().playback.crossfade_secs > 0.0
+            && next.is_some_and(|(_, t)| !same_album(&t.album, &st.album));
+        st.next_album = next.map(|(_, t)| t.album.clone()).unwrap_or_default();
+        st.crossfade = crossfade;
+        if crossfade {
+            self.warm_spare();
+        }
+
+        // playlist-clear keeps the playing file and drops the old preload
+        let _ = ipc.command(json!(["playlist-clear"]));
+        st.preloaded = match want {
+            Some(url) if crossfade => Some(url),
+            Some(url) if loadfile(&ipc, &url, "append", None).is_ok() => Some(url),
+            _ => None,
+        };
+    }
+
+    fn stop(&self) {
+        let mut st = self.state.lock().unwrap();
+        let fade = fade_duration();
+        let playing = st.ipc.as_ref().is_some_and(|i| !i.props().pause);
+        if st.loaded && playing && !fade.is_zero() {
+            // fades out on the side, the next load starts a fresh mpv
+            self.retire(&mut st, fade);
+            return;
+        }
+        if st.loaded
+            && let Some(ipc) = &st.ipc
+        {
+            ipc.send(json!(["stop"]));
+        }
+        st.loaded = false;
+        st.preloaded = None;
+        st.crossfade = false;
+    }
+
+    fn set_filter(&self, label: &str, filter: Option<&str>) {
+        {
+            let mut filters = self.filters.lock().unwrap();
+            // re-adding an identical filter would still reinit mpv's audio chain
+            if filters.get(label).map(String::as_str) == filter {
+                return;
+            }
+            match filter {
+                Some(g) => filters.insert(label.to_string(), g.to_string()),
+                None => filters.remove(label),
+            };
+        }
+        self.drop_spare();
+        // not running yet: spawn_mpv picks it up
+        let Some(ipc) = self.ipc() else {
+            return;
+        };
+        let _ = ipc.command(json!(["af", "remove", format!("@{}", label)]));
+        if let Some(f) = filter
+            && let Err(e) = ipc.command(json!(["af", "add", af_entry(label, f)]))
+        {
+            crate::ui_common::set_status_line(Some(format!(":( Audio filter failed: {}", e)));
+        }
+    }
+
+    fn audio_devices(&self) -> Vec<(String, String)> {
+        let ipc = {
+            let mut st = self.state.lock().unwrap();
+            let volume = st.volume;
+            match self.ensure_running(&mut st, volume) {
+                Ok(ipc) => ipc,
+                Err(_) => return Vec::new(),
+            }
+        };
+        ipc.command(json!(["get_property", "audio-device-list"]))
+            .ok()
+            .and_then(|list| crate::mpv_ipc::parse_devices(&list))
+            .unwrap_or_default()
+    }
+
+    fn set_audio_device(&self, name: &str) {
+        let mut st = self.state.lock().unwrap();
+        st.device = name.to_string();
+        // not running yet: ensure_running routes it
+        let Some(ipc) = st.ipc.clone() else {
+            return;
+        };
+        let devices = ipc
+            .command(json!(["get_property", "audio-device-list"]))
+            .ok()
+            .and_then(|list| crate::mpv_ipc::parse_devices(&list))
+            .unwrap_or_default();
+        route_device(&mut st, &ipc, &devices);
+    }
+
+    fn shutdown(&self) {
+        let mut st = self.state.lock().unwrap();
+        if let Some(ipc) = &st.ipc {
+            ipc.send(json!(["quit"]));
+        }
+        if let Some(mut child) = st.child.take() {
+            kill_child(&mut child);
+        }
+        *st = MpvState::default();
+        for mut child in self.retired.lock().unwrap().drain(..) {
+            kill_child(&mut child);
+        }
+        self.spare.lock().unwrap().closed = true;
+        self.drop_spare();
+    }
+}
+
+// ---- NULL ----
+// plays nothing. a virtual clock moves the track forward a fixed step on
+// every poll_event instead of following real time, so the same calls give
+// the same positions, ends and switches every run
+
+// track seconds per poll at null_speed 1. the main loop polls about every
+// 250ms, so that is roughly real time
+const NULL_TICK: f64 = 0.25;
+
+struct NullTrack {
+    duration: f64,
+    position: f64,
+    paused: bool,
+    speed: f64,
+}
+
+pub struct NullBackend {
+    track: Mutex<Option<NullTrack>>,
+    // (source, duration) of the preloaded track
+    next: Mutex<Option<(String, f64)>>,
+    // seconds one poll moves the clock at playback speed 1
+    step: f64,
+}
+
+// tracks without a known length still need to end
+const NULL_DEFAULT_LENGTH: f64 = 180.0;
+
+fn null_length(track: &Track) -> f64 {
+    let length = crate::ui_common::duration_to_seconds(&track.duration);
+    if length > 0.0 { length } else { NULL_DEFAULT_LENGTH }
+}
+
+impl NullBackend {
+    pub fn new(step: f64) -> Self {
+        Self {
+            track: Mutex::new(None),
+            next: Mutex::new(None),
+            step,
+        }
+    }
+}
+
+impl PlayerBackend for NullBackend {
+    fn load(
+        &self,
+        _source: &str,
+        track: &Track,
+        start: Option<f64>,
+        _volume: i64,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let duration = null_length(track);
+        *self.track.lock().unwrap() = Some(NullTrack {
+            duration,
+            position: start.unwrap_or(0.0).clamp(0.0, duration),
+            paused: false,
+            speed: crate::tempo::speed(),
+        });
+        *self.next.lock().unwrap() = None;
+        Ok(())
+    }
+
+    fn toggle_pause(&self) {
+        if let Some(t) = self.track.lock().unwrap().as_mut() {
+            t.paused = !t.paused;
+        }
+    }
+
+    fn is_paused(&self) -> bool {
+        self.track.lock().unwrap().as_ref().is_some_and(|t| t.paused)
+    }
+
+    fn seek(&self, seconds: i64) {
+        if let Some(t) = self.track.lock().unwrap().as_mut() {
+            t.position = (t.position + seconds as f64).clamp(0.0, t.duration);
+        }
+    }
+
+    fn set_volume(&self, _volume: i64) {}
+
+    fn set_speed(&self, speed: f64) {
+        if let Some(t) = self.track.lock().unwrap().as_mut() {
+            t.speed = speed;
+        }
+    }
+
+    fn position(&self) -> Option<(f64, f64)> {
+        let guard = self.track.lock().unwrap();
+        let t = guard.as_ref()?;
+        Some((t.position, t.duration))
+    }
+
+    fn poll_event(&self) -> Option<PlayerEvent> {
+        let mut guard = self.track.lock().unwrap();
+        let t = guard.as_mut()?;
+        if !t.paused {
+            t.position = (t.position + self.step * t.speed).min(t.duration);
+        }
+        if t.position < t.duration {
+            return None;
+        }
+        match self.next.lock().unwrap().take() {
+            Some((source, duration)) => {
+                t.duration = duration;
+                t.position = 0.0;
+                Some(PlayerEvent::Advanced { source })
+            }
+            None => {
+                let position = Some((t.position, t.duration));
+                *guard = None;
+                Some(PlayerEvent::Ended {
+                    error: None,
+                    position,
+                })
+            }
+        }
+    }
+
+    fn preload(&self, next: Option<(&str, &Track)>) {
+        *self.next.lock().unwrap() = next.map(|(source, track)| (source.to_string(), null_length(track)));
+    }
+
+    fn stop(&self) {
+        *self.track.lock().unwrap() = None;
+        *self.next.lock().unwrap() = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(duration: &str) -> Track {
+        Track {
+            duration: duration.to_string(),
+            ..Track::sample(Some("abc"), "")
+        }
+    }
+
+    // played to the end of a track `length` seconds long
+    fn ended(length: f64) -> PlayerEvent {
+        PlayerEvent::Ended {
+            error: None,
+            position: Some((length, length)),
+        }
+    }
+
+    // a 10 second step per poll
+    fn null() -> NullBackend {
+        NullBackend::new(10.0)
+    }
+
+    #[test]
+    fn null_clock_moves_one_step_per_poll() {
+        let b = null();
+        assert_eq!(b.position(), None);
+        b.load("a", &track("0:30"), None, 100).unwrap();
+        assert_eq!(b.position(), Some((0.0, 30.0)));
+        assert_eq!(b.poll_event(), None);
+        assert_eq!(b.poll_event(), None);
+        assert_eq!(b.position(), Some((20.0, 30.0)));
+        assert_eq!(b.poll_event(), Some(ended(30.0)));
+        assert_eq!(b.position(), None);
+        assert_eq!(b.poll_event(), None);
+    }
+
+    #[test]
+    fn null_pause_seek_and_speed() {
+        let b = null();
+        b.load("a", &track("1:00"), Some(5.0), 100).unwrap();
+        b.toggle_pause();
+        assert!(b.is_paused());
+        b.poll_event();
+        assert_eq!(b.position(), Some((5.0, 60.0)));
+        b.toggle_pause();
+        b.seek(-20);
+        assert_eq!(b.position(), Some((0.0, 60.0)));
+        b.seek(45);
+        b.set_speed(0.5);
+        b.poll_event();
+        assert_eq!(b.position(), Some((50.0, 60.0)));
+        // a start past the end is the end
+        b.load("a", &track("1:00"), Some(90.0), 100).unwrap();
+        assert_eq!(b.position(), Some((60.0, 60.0)));
+    }
+
+    #[test]
+    fn null_switches_to_the_preloaded_track() {
+        let b = null();
+        b.load("a", &track("0:10"), None, 100).unwrap();
+        b.preload(Some(("b", &track(""))));
+        assert_eq!(
+            b.poll_event(),
+            Some(PlayerEvent::Advanced {
+                source: "b".to_string()
+            })
+        );
+        // no length: the default one
+        assert_eq!(b.position(), Some((0.0, NULL_DEFAULT_LENGTH)));
+        b.preload(Some(("c", &track("0:10"))));
+        b.preload(None);
+        b.seek(1000);
+        assert_eq!(b.poll_event(), Some(ended(NULL_DEFAULT_LENGTH)));
+    }
+
+    #[test]
+    fn null_stop_drops_track_and_preload() {
+        let b = null();
+        b.load("a", &track("0:10"), None, 100).unwrap();
+        b.preload(Some(("b", &track("0:10"))));
+        b.stop();
+        assert_eq!(b.position(), None);
+        assert_eq!(b.poll_event(), None);
+        b.load("c", &track("0:05"), None, 100).unwrap();
+        assert_eq!(b.poll_event(), Some(ended(5.0)));
+    }
+}
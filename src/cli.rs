@@ -0,0 +1,764 @@
+use crate::api::{self, SongDetails};
+use crate::backend::PlayerEvent;
+use crate::features::fetch_synced_lyrics;
+use crate::flac::{fetch_flac_stream_url, init_api};
+use crate::settings::BackendKind;
+use crate::{Track, config, player};
+use std::path::{Path, PathBuf};
+
+// -------------------------------------------------------------------
+// COMMAND LINE PARSING
+// no subcommand = launch the tui like before
+// -------------------------------------------------------------------
+
+pub const HELP: &str = "\
+whytui - terminal YouTube Music player
+
+USAGE:
+    whytui [FLAGS]                         launch the TUI
+    whytui search <query> [--json] [--limit N]
+    whytui play <videoId|url|path>         play one track without the TUI
+    whytui download <playlistId> [--limit N]
+    whytui lyrics <query> [--plain]
+    whytui stats [--window W] [--limit N] [--year YYYY] [--report FILE]
+                                           listening stats, W = week|month|year|all|<N>d
+                                           --report writes a year-in-review (.html or .md)
+    whytui ctl <command> [--json]          control a running whytui:
+                                           play-pause | play | pause | next | previous | like
+                                           seek <+-secs> | volume <N|+N|-N> | status
+                                           enqueue <query> | enqueue id:<videoId>
+                                           eq [preset] | speed <X|+X|-X> | pitch <N|+N|-N>
+                                           sleep <min|track|N tracks|off>
+                                           alarm <HH:MM> [offline|playlistId] | alarm off
+                                           device [name|auto]
+
+FLAGS:
+    -d, --download        save played songs to the music dir
+    -o, --offline         only play songs from the music dir
+    -n, --nomix           disable autoplay
+    -l, --lossless        try fetching lossless audio
+    -pl, --peak-lossless  try fetching hi-res lossless audio
+    -g, --guess           guess the quality of the playing song
+    --backend <mpv|null>  audio backend, null plays nothing (headless/ci)
+    --profile <name>      stream quality from [stream.profiles.<name>] in config.toml
+    --sleep <spec>        stop after N minutes, 'track' or 'N tracks'
+    --alarm <HH:MM>       start the offline shuffle at that time ('HH:MM <playlistId>' for a playlist)
+    -h, --help            print this help
+    -V, --version         print the version
+";
+
+#[derive(Debug, Default)]
+pub struct Flags {
+    pub download: bool,
+    pub offline: bool,
+    pub nomix: bool,
+    pub lossless: bool,
+    pub peak_lossless: bool,
+    pub guess: bool,
+    pub backend: Option<BackendKind>,
+    // timers to set once the tui is up
+    pub sleep: Option<String>,
+    pub alarm: Option<String>,
+    // [stream.profiles] entry to use
+    pub profile: Option<String>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Tui,
+    Help,
+    Version,
+    Search {
+        query: String,
+        json: bool,
+        limit: Option<usize>,
+    },
+    Play {
+        target: String,
+    },
+    Download {
+        playlist_id: String,
+        limit: usize,
+    },
+    Lyrics {
+        query: String,
+        plain: bool,
+    },
+    Stats {
+        window: Option<String>,
+        limit: Option<usize>,
+        year: Option<i32>,
+        report: Option<PathBuf>,
+    },
+    Ctl {
+        args: Vec<String>,
+        json: bool,
+    },
+}
+
+impl Command {
+    // talks to music.youtube.com (stats, ctl, help and version don't)
+    pub fn uses_youtube(&self) -> bool {
+        matches!(
+            self,
+            Command::Tui
+                | Command::Search { .. }
+                | Command::Play { .. }
+                | Command::Download { .. }
+                | Command::Lyrics { .. }
+        )
+    }
+}
+
+pub struct Cli {
+    pub command: Command,
+    pub flags: Flags,
+}
+
+pub fn parse_args(args: &[String]) -> Result<Cli, String> {
+    let mut flags = Flags::default();
+    let mut positional: Vec<String> = Vec::new();
+    let mut json = false;
+    let mut plain = false;
+    let mut limit: Option<usize> = None;
+    let mut window: Option<String> = None;
+    let mut year: Option<i32> = None;
+    let mut report: Option<PathBuf> = None;
+    let mut help = false;
+    let mut version = false;
+
+    let mut iter = args.iter().skip(1);
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-d" | "--download" => flags.download = true,
+            "-o" | "--offline" => flags.offline = true,
+            "-n" | "--nomix" => flags.nomix = true,
+            "-l" | "--lossless" => flags.lossless = true,
+            "-pl" | "--peak-lossless" => flags.peak_lossless = true,
+            "-g" | "--guess" => flags.guess = true,
+            "-h" | "--help" => help = true,
+            "-V" | "--version" => version = true,
+            "--backend" => {
+                let value = iter.next().ok_or("--backend needs mpv or null")?;
+                flags.backend = Some(match value.as_str() {
+                    "mpv" => BackendKind::Mpv,
+                    "null" => BackendKind::Null,
+                    _ => return Err(format!("--backend expects mpv or null, got '{}'", value)),
+                });
+            }
+            "--sleep" => {
+                let value = iter.next().ok_or("--sleep needs minutes, track or 'N tracks'")?;
+                crate::timer::parse_sleep(value)?;
+                flags.sleep = Some(value.clone());
+            }
+            "--alarm" => {
+                let value = iter.next().ok_or("--alarm needs a time like 07:30")?;
+                crate::timer::parse_alarm(value)?;
+                flags.alarm = Some(value.clone());
+            }
+            "--profile" => {
+                let value = iter.next().ok_or("--profile needs a profile name")?;
+                flags.profile = Some(value.clone());
+            }
+            "--json" => json = true,
+            "--plain" => plain = true,
+            "--limit" => {
+                let value = iter.next().ok_or("--limit needs a number")?;
+                limit = Some(
+                    value
+                        .parse::<usize>()
+                        .map_err(|_| format!("--limit expects a number, got '{}'", value))?,
+                );
+            }
+            "--window" => {
+                let value = iter.next().ok_or("--window needs a value")?;
+                crate::stats::parse_window(value)?;
+                window = Some(value.clone());
+            }
+            "--year" => {
+                let value = iter.next().ok_or("--year needs a year")?;
+                year = Some(
+                    value
+                        .parse::<i32>()
+                        .map_err(|_| format!("--year expects a year, got '{}'", value))?,
+                );
+            }
+            "--report" => {
+                let value = iter.next().ok_or("--report needs a file name")?;
+                report = Some(PathBuf::from(value));
+            }
+            "--" => positional.extend(iter.by_ref().cloned()),
+            // negative numbers are values (ctl seek -10, ctl speed -0.25), not flags
+            s if s.starts_with('-') && s.len() > 1 && s.parse::<f64>().is_err() => {
+                return Err(format!("unknown flag '{}'", s));
+            }
+            _ => positional.push(arg.clone()),
+        }
+    }
+
+    if help {
+        return Ok(Cli {
+            command: Command::Help,
+            flags,
+        });
+    }
+    if version {
+        return Ok(Cli {
+            command: Command::Version,
+            flags,
+        });
+    }
+
+    let sub = positional.first().cloned();
+    let rest = positional.get(1..).unwrap_or_default().join(" ");
+
+    let command = match sub.as_deref() {
+        None => Command::Tui,
+        Some("search") => Command::Search {
+            query: required(rest, "search", "<query>")?,
+            json,
+            limit,
+        },
+        Some("play") => Command::Play {
+            target: required(rest, "play", "<videoId|url|path>")?,
+        },
+        Some("download") => Command::Download {
+            playlist_id: required(rest, "download", "<playlistId>")?,
+            limit: limit.unwrap_or(usize::MAX),
+        },
+        Some("lyrics") => Command::Lyrics {
+            query: required(rest, "lyrics", "<query>")?,
+            plain,
+        },
+        Some("stats") => {
+            if window.is_some() && year.is_some() {
+                return Err("--window and --year can't be used together".to_string());
+            }
+            Command::Stats {
+                window: window.take(),
+                limit,
+                year,
+                report: report.take(),
+            }
+        }
+        Some("ctl") => {
+            let args = positional[1..].to_vec();
+            crate::control::parse_request(&args)?;
+            Command::Ctl { args, json }
+        }
+        Some(other) => return Err(format!("unknown command '{}'", other)),
+    };
+
+    if (flags.sleep.is_some() || flags.alarm.is_some()) && command != Command::Tui {
+        return Err("--sleep and --alarm only work when starting the TUI".to_string());
+    }
+    if window.is_some() || year.is_some() || report.is_some() {
+        return Err("--window, --year and --report only work with 'stats'".to_string());
+    }
+
+    // subcommand-only switches make no sense for the tui
+    if command == Command::Tui && (json || plain || limit.is_some()) {
+        return Err("--json, --plain and --limit need a subcommand".to_string());
+    }
+
+    Ok(Cli { command, flags })
+}
+
+fn required(value: String, command: &str, what: &str) -> Result<String, String> {
+    if value.trim().is_empty() {
+        Err(format!("'{}' needs {}", command, what))
+    } else {
+        Ok(value.trim().to_string())
+    }
+}
+
+// -------------------------------------------------------------------
+// NON-INTERACTIVE MODES
+// -------------------------------------------------------------------
+
+pub async fn run(
+    command: Command,
+    yt: &api::YTMusic,
+    music_dir: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match command {
+        // handled in main before any config is loaded
+        Command::Tui | Command::Help | Command::Version => Ok(()),
+        Command::Search { query, json, limit } => {
+            let limit = limit.unwrap_or(config().ui.search_results);
+            search(yt, &query, json, limit).await
+        }
+        Command::Play { target } => play(yt, &target, music_dir).await,
+        Command::Download { playlist_id, limit } => {
+            download(yt, &playlist_id, limit, music_dir).await
+        }
+        Command::Lyrics { query, plain } => lyrics(yt, &query, plain).await,
+        Command::Stats {
+            window,
+            limit,
+            year,
+            report,
+        } => stats(window, limit, year, report),
+        Command::Ctl { args, json } => ctl(&args, json),
+    }
+}
+
+async fn search(
+    yt: &api::YTMusic,
+    query: &str,
+    json: bool,
+    limit: usize,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let songs = yt.search_songs(query, limit).await?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&songs)?);
+        return Ok(());
+    }
+
+    if songs.is_empty() {
+        return Err("No results".into());
+    }
+    for s in &songs {
+        println!(
+            "{}\t{}\t{}\t{}\t{}",
+            s.video_id,
+            s.title,
+            s.artists.join(", "),
+            s.album,
+            s.duration
+        );
+    }
+    Ok(())
+}
+
+async fn play(
+    yt: &api::YTMusic,
+    target: &str,
+    music_dir: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (source, track) = if Path::new(target).exists() {
+        let title = Path::new(target)
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_else(|| target.to_string());
+        (target.to_string(), track_with_title(title, None))
+    } else if let Some(video_id) = extract_video_id(target) {
+        // the bare id only when the details can't be had
+        let track = match yt.fetch_song_details(&video_id).await {
+            Ok(song) => Track::new(
+                song.title,
+                song.artists,
+                song.album,
+                song.duration,
+                song.thumbnail_url,
+                Some(video_id),
+                String::new(),
+            ),
+            Err(_) => track_with_title(video_id.clone(), Some(video_id)),
+        };
+        // through the resolver, so mpv knows which client the url is for
+        let url = crate::resolve::stream_url(&track, false).await.map_err(|e| e.to_string())?;
+        (url, track)
+    } else if target.starts_with("http") {
+        (
+            target.to_string(),
+            track_with_title(target.to_string(), None),
+        )
+    } else {
+        return Err(format!("'{}' is not a file, URL or videoId", target).into());
+    };
+
+    println!("Playing {}", track.title);
+    let mut playback = player::play_file(&source, &track, &music_dir.to_path_buf())?;
+    loop {
+        if let Some(PlayerEvent::Ended { error, .. }) = playback.poll() {
+            player::shutdown();
+            return match error {
+                Some(e) => Err(e.into()),
+                None => Ok(()),
+            };
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+    }
+}
+
+async fn download(
+    yt: &api::YTMusic,
+    playlist_id: &str,
+    limit: usize,
+    music_dir: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if config().lossless_mode
+        && let Err(e) = init_api().await
+    {
+        eprintln!("FLAC API Init failed: {}", e);
+    }
+
+    let (songs, _) = yt.fetch_playlist_songs(playlist_id, limit).await?;
+    if songs.is_empty() {
+        return Err(format!("No songs found in playlist {}", playlist_id).into());
+    }
+
+    let total = songs.len();
+    let mut failed = 0;
+    for (i, song) in songs.into_iter().enumerate() {
+        print!(
+            "[{}/{}] {} - {} ... ",
+            i + 1,
+            total,
+            song.title,
+            song.artists.join(", ")
+        );
+
+        match download_one(yt, song, music_dir).await {
+            Ok(true) => println!("ok"),
+            Ok(false) => println!("already saved"),
+            Err(e) => {
+                failed += 1;
+                println!("failed ({})", e);
+            }
+        }
+    }
+
+    if failed > 0 {
+        return Err(format!("{} of {} downloads failed", failed, total).into());
+    }
+    Ok(())
+}
+
+async fn download_one(
+    yt: &api::YTMusic,
+    song: SongDetails,
+    music_dir: &Path,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let file_name = player::the_naming_format_in_which_i_have_saved_the_track_locally(
+        &song.title,
+        &song.artists,
+    );
+    if music_dir.join(format!("{}.flac", file_name)).exists()
+        || music_dir.join(format!("{}.opus", file_name)).exists()
+    {
+        return Ok(false);
+    }
+
+    let mut url = None;
+    if config().lossless_mode && !crate::quality::data_saver() {
+        let query = format!("{} {}", song.title, song.artists.join(" "));
+        url = fetch_flac_stream_url(&query, &song.duration).await.ok();
+    }
+    if url.is_none() {
+        url = Some(yt.fetch_stream_url(&song.video_id).await?);
+    }
+    let url = url.ok_or("No stream found")?;
+
+    let track = Track::new(
+        song.title,
+        song.artists,
+        song.album,
+        song.duration,
+        song.thumbnail_url,
+        Some(song.video_id),
+        url.clone(),
+    );
+
+    let dir = music_dir.to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        player::background_download(&url, &track, &dir).map_err(|e| e.to_string())
+    })
+    .await??;
+    Ok(true)
+}
+
+async fn lyrics(
+    yt: &api::YTMusic,
+    query: &str,
+    plain: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let song = yt
+        .search_songs(query, 1)
+        .await?
+        .into_iter()
+        .next()
+        .ok_or("No results")?;
+
+    let track = Track::new(
+        song.title,
+        song.artists,
+        song.album,
+        song.duration,
+        song.thumbnail_url,
+        Some(song.video_id),
+        String::new(),
+    );
+    let lines = fetch_synced_lyrics(&track)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    for line in lines {
+        if plain {
+            println!("{}", line.text);
+        } else {
+            let ms = line.timestamp.as_millis();
+            println!(
+                "[{:02}:{:02}.{:02}] {}",
+                ms / 60000,
+                (ms / 1000) % 60,
+                (ms % 1000) / 10,
+                line.text
+            );
+        }
+    }
+    Ok(())
+}
+
+fn stats(
+    window: Option<String>,
+    limit: Option<usize>,
+    year: Option<i32>,
+    report: Option<PathBuf>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let entries = crate::history::load_all();
+    let top = limit.unwrap_or(config().stats.top);
+
+    // a report is always a whole calendar year
+    if let Some(path) = report {
+        let year = year.unwrap_or_else(crate::stats::current_year);
+        let range = crate::stats::year_range(year).ok_or("Invalid year")?;
+        let stats = crate::stats::compute(&entries, range, top);
+
+        let is_html = path
+            .extension()
+            .map(|e| e.eq_ignore_ascii_case("html") || e.eq_ignore_ascii_case("htm"))
+            .unwrap_or(false);
+        let content = if is_html {
+            crate::stats::report_html(&stats, year)
+        } else {
+            crate::stats::report_markdown(&stats, year)
+        };
+        std::fs::write(&path, content)?;
+        println!("Wrote {} report to {}", year, path.display());
+        return Ok(());
+    }
+
+    let (range, title) = match year {
+        Some(y) => (
+            crate::stats::year_range(y).ok_or("Invalid year")?,
+            y.to_string(),
+        ),
+        None => {
+            let w = crate::stats::parse_window(
+                window.as_deref().unwrap_or(&config().stats.window),
+            )?;
+            (w.range(), w.label())
+        }
+    };
+    let stats = crate::stats::compute(&entries, range, top);
+    for line in crate::stats::render_lines(&stats, &title) {
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+fn ctl(args: &[String], json: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let req = crate::control::parse_request(args)?;
+    let resp = crate::control::send_request(&req)?;
+
+    if json {
+        println!("{}", resp);
+    }
+    if resp["ok"].as_bool() != Some(true) {
+        return Err(resp["error"].as_str().unwrap_or("request failed").into());
+    }
+    if json {
+        return Ok(());
+    }
+
+    if let Some(devices) = resp["devices"].as_array() {
+        for d in devices {
+            let mark = if d["current"].as_bool() == Some(true) { "*" } else { " " };
+            println!(
+                "{} {}  ({})",
+                mark,
+                d["name"].as_str().unwrap_or(""),
+                d["description"].as_str().unwrap_or("")
+            );
+        }
+    }
+    if let Some(status) = resp.get("status") {
+        let st: crate::control::Status = serde_json::from_value(status.clone())?;
+        match &st.title {
+            Some(title) => {
+                println!("{} - {}", title, st.artists.join(", "));
+                let mmss = |t: f64| format!("{}:{:02}", t as u64 / 60, t as u64 % 60);
+                println!("{}  {} / {}", st.state, mmss(st.position), mmss(st.duration));
+            }
+            None => println!("{}", st.state),
+        }
+        println!(
+            "volume {}  queue {}  effects {}  speed {}x  pitch {:+}",
+            st.volume, st.queue, st.effects, st.speed, st.pitch
+        );
+        for timer in [&st.sleep, &st.alarm].into_iter().flatten() {
+            println!("{}", timer.to_lowercase());
+        }
+    }
+    Ok(())
+}
+
+fn track_with_title(title: String, video_id: Option<String>) -> Track {
+    let mut track = Track::dummy();
+    track.title = title;
+    track.video_id = video_id;
+    track
+}
+
+// accepts a bare videoId or any youtube / music.youtube / youtu.be / shorts link
+pub fn extract_video_id(target: &str) -> Option<String> {
+    let is_id = |s: &str| {
+        s.len() == 11
+            && s.chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    };
+
+    if is_id(target) && !PathBuf::from(target).exists() {
+        return Some(target.to_string());
+    }
+    if !target.contains("youtube.com") && !target.contains("youtu.be") {
+        return None;
+    }
+
+    // the v key itself, not the end of dev= or rev=
+    let query = target
+        .split_once('?')
+        .map(|(_, q)| q.split('#').next().unwrap_or(""));
+    if let Some(id) = query.and_then(|q| q.split('&').find_map(|kv| kv.strip_prefix("v="))) {
+        return is_id(id).then(|| id.to_string());
+    }
+    for marker in ["youtu.be/", "/shorts/"] {
+        if let Some(pos) = target.find(marker) {
+            let id: String = target[pos + marker.len()..]
+                .chars()
+                .take_while(|c| !matches!(c, '?' | '&' | '/' | '#'))
+                .collect();
+            return is_id(&id).then_some(id);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(line: &str) -> Result<Cli, String> {
+        let args: Vec<String> = std::iter::once("whytui")
+            .chain(line.split_whitespace())
+            .map(String::from)
+            .collect();
+        parse_args(&args)
+    }
+
+    #[test]
+    fn no_arguments_start_the_tui() {
+        let cli = parse("").unwrap();
+        assert_eq!(cli.command, Command::Tui);
+        let cli = parse("-d --offline -n").unwrap();
+        assert_eq!(cli.command, Command::Tui);
+        assert!(cli.flags.download && cli.flags.offline && cli.flags.nomix);
+    }
+
+    #[test]
+    fn subcommands_take_the_rest_as_their_argument() {
+        assert_eq!(
+            parse("search daft punk --json --limit 3").unwrap().command,
+            Command::Search {
+                query: "daft punk".to_string(),
+                json: true,
+                limit: Some(3),
+            }
+        );
+        assert_eq!(
+            parse("lyrics one more time --plain").unwrap().command,
+            Command::Lyrics {
+                query: "one more time".to_string(),
+                plain: true,
+            }
+        );
+        assert_eq!(
+            parse("download PL123").unwrap().command,
+            Command::Download {
+                playlist_id: "PL123".to_string(),
+                limit: usize::MAX,
+            }
+        );
+    }
+
+    #[test]
+    fn help_and_version_win() {
+        assert_eq!(parse("search x --help").unwrap().command, Command::Help);
+        assert_eq!(parse("-V").unwrap().command, Command::Version);
+    }
+
+    #[test]
+    fn rejects_bad_input() {
+        assert!(parse("--nope").is_err());
+        assert!(parse("frobnicate").is_err());
+        assert!(parse("search").is_err());
+        assert!(parse("--limit").is_err());
+        assert!(parse("search x --limit many").is_err());
+        assert!(parse("--backend alsa").is_err());
+        // subcommand-only switches
+        assert!(parse("--json").is_err());
+        assert!(parse("search x --window week").is_err());
+        assert!(parse("stats --window week --year 2025").is_err());
+        assert!(parse("play abc --sleep 30").is_err());
+    }
+
+    #[test]
+    fn negative_numbers_are_values() {
+        let cli = parse("ctl seek -10").unwrap();
+        assert_eq!(
+            cli.command,
+            Command::Ctl {
+                args: vec!["seek".to_string(), "-10".to_string()],
+                json: false,
+            }
+        );
+    }
+
+    #[test]
+    fn tui_flags_with_values() {
+        let cli = parse("--backend null --sleep 30 --profile car").unwrap();
+        assert_eq!(cli.flags.backend, Some(BackendKind::Null));
+        assert_eq!(cli.flags.sleep.as_deref(), Some("30"));
+        assert_eq!(cli.flags.profile.as_deref(), Some("car"));
+    }
+
+    #[test]
+    fn only_youtube_commands_use_youtube() {
+        assert!(parse("").unwrap().command.uses_youtube());
+        assert!(parse("search x").unwrap().command.uses_youtube());
+        assert!(!parse("stats").unwrap().command.uses_youtube());
+        assert!(!parse("ctl status").unwrap().command.uses_youtube());
+    }
+
+    #[test]
+    fn video_ids_from_links() {
+        let id = Some("dQw4w9WgXcQ".to_string());
+        assert_eq!(extract_video_id("dQw4w9WgXcQ"), id);
+        assert_eq!(
+            extract_video_id("https://music.youtube.com/watch?v=dQw4w9WgXcQ&list=RD"),
+            id
+        );
+        assert_eq!(extract_video_id("https://youtu.be/dQw4w9WgXcQ?t=3"), id);
+        assert_eq!(
+            extract_video_id("https://www.youtube.com/watch?dev=1&rev=2&v=dQw4w9WgXcQ"),
+            id
+        );
+        assert_eq!(extract_video_id("https://youtube.com/shorts/dQw4w9WgXcQ?feature=share"), id);
+        assert_eq!(extract_video_id("https://youtube.com/watch?dev=dQw4w9WgXcQ"), None);
+        assert_eq!(extract_video_id("https://example.com/watch?v=dQw4w9WgXcQ"), None);
+        assert_eq!(extract_video_id("song.opus"), None);
+    }
+}
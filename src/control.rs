@@ -0,0 +1,558 @@
+use crate::cli::Command;
+use crate::{SONG_QUEUE, config, player};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::sync::atomic::Ordering;
+use std::sync::mpsc::Sender;
+
+// -------------------------------------------------------------------
+// remote control over a unix socket (separate from mpv's socket), one
+// per instance; `whytui ctl` talks to the primary one (see instance.rs)
+// one json request per line in, one json response per line out:
+//   {"command":"next"}                     -> {"ok":true}
+//   {"command":"seek","seconds":-10}
+//   {"command":"volume","value":60}        (or "delta":5)
+//   {"command":"enqueue","query":"..."}    (or "video_id":"...")
+//   {"command":"effects","preset":"bass"}  (no preset = next one)
+//   {"command":"speed","value":1.5}        (or "delta":0.25)
+//   {"command":"pitch","value":-2}         (semitones, or "delta":1)
+//   {"command":"sleep","spec":"30"}        ("track", "3 tracks", "off")
+//   {"command":"alarm","spec":"07:30"}     ("07:30 <playlistId>", "off")
+//   {"command":"device","name":"pulse/..."} (no name -> {"ok":true,"devices":[...]})
+//   {"command":"status"}                   -> {"ok":true,"status":{...}}
+//   {"command":"launch","args":["play","<id>"]}  (a forwarded launch)
+// requests become the same strings the keyboard sends, so the main loop
+// treats them exactly like key presses. they travel on their own channel
+// that menus never read: a `next` waits for an open menu to close instead
+// of paging it
+// -------------------------------------------------------------------
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "kebab-case", deny_unknown_fields)]
+pub enum Request {
+    PlayPause,
+    Play,
+    Pause,
+    Next,
+    Previous,
+    Seek {
+        seconds: i64,
+    },
+    Volume {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        value: Option<i64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        delta: Option<i64>,
+    },
+    Enqueue {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        query: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        video_id: Option<String>,
+    },
+    Like,
+    // no preset = next one, like the `e` key
+    Effects {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        preset: Option<String>,
+    },
+    Speed {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        value: Option<f64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        delta: Option<f64>,
+    },
+    Pitch {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        value: Option<i64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        delta: Option<i64>,
+    },
+    // same words as typing "sleep=30" / "alarm=07:30" into the tui
+    Sleep {
+        spec: String,
+    },
+    Alarm {
+        spec: String,
+    },
+    // no name = list them
+    Device {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
+    },
+    Status,
+    Launch {
+        args: Vec<String>,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Status {
+    // playing, paused or stopped
+    pub state: String,
+    pub title: Option<String>,
+    pub artists: Vec<String>,
+    pub album: Option<String>,
+    pub video_id: Option<String>,
+    pub position: f64,
+    pub duration: f64,
+    pub volume: i64,
+    pub repeat: usize,
+    pub queue: usize,
+    pub effects: String,
+    pub speed: f64,
+    // semitones
+    pub pitch: i64,
+    pub sleep: Option<String>,
+    pub alarm: Option<String>,
+}
+
+pub fn socket_path() -> PathBuf {
+    crate::instance::ctl_socket()
+}
+
+fn playback_state() -> &'static str {
+    if crate::history::current_track().is_none() {
+        "stopped"
+    } else if player::is_paused() {
+        "paused"
+    } else {
+        "playing"
+    }
+}
+
+fn status() -> Status {
+    let track = crate::history::current_track();
+    let (position, duration) = player::get_time_info().unwrap_or((0.0, 0.0));
+    Status {
+        state: playback_state().to_string(),
+        title: track.as_ref().map(|t| t.title.clone()),
+        artists: track.as_ref().map(|t| t.artists.clone()).unwrap_or_default(),
+        album: track.as_ref().map(|t| t.album.clone()),
+        video_id: track.as_ref().and_then(|t| t.video_id.clone()),
+        position,
+        duration,
+        volume: crate::VOLUME.load(Ordering::Relaxed),
+        repeat: crate::REPEAT_MODE.load(Ordering::Relaxed),
+        queue: SONG_QUEUE.read().unwrap().len(),
+        effects: crate::effects::current(),
+        speed: crate::tempo::speed(),
+        pitch: crate::tempo::pitch(),
+        sleep: crate::timer::sleep_text(),
+        alarm: crate::timer::alarm_text(),
+    }
+}
+
+// turns a request into the input string(s) handle_global_commands understands
+fn to_commands(req: Request) -> Result<Vec<String>, String> {
+    let cmds = match req {
+        Request::PlayPause => vec!["pause".to_string()],
+        Request::Play => {
+            if player::is_paused() {
+                vec!["pause".to_string()]
+            } else {
+                Vec::new()
+            }
+        }
+        Request::Pause => {
+            if playback_state() == "playing" {
+                vec!["pause".to_string()]
+            } else {
+                Vec::new()
+            }
+        }
+        Request::Next => vec!["n".to_string()],
+        Request::Previous => vec!["p".to_string()],
+        Request::Seek { seconds } if seconds < 0 => vec![format!("<{}", -seconds)],
+        Request::Seek { seconds } => vec![format!(">{}", seconds)],
+        Request::Volume { value: Some(v), .. } => vec![format!("vol={}", v)],
+        Request::Volume {
+            delta: Some(d), ..
+        } => vec![format!("vol={:+}", d)],
+        Request::Volume { .. } => return Err("volume needs 'value' or 'delta'".to_string()),
+        Request::Enqueue {
+            video_id: Some(id), ..
+        } => vec![format!("enqueue_id={}", id.trim())],
+        Request::Enqueue { query: Some(q), .. } if !q.trim().is_empty() => {
+            vec![format!("enqueue={}", q.trim())]
+        }
+        Request::Enqueue { .. } => return Err("enqueue needs 'query' or 'video_id'".to_string()),
+        Request::Like => vec!["l".to_string()],
+        Request::Effects { preset: Some(p) } => {
+            if !crate::effects::preset_names().contains(&p) {
+                return Err(format!(
+                    "unknown preset '{}' (known: {})",
+                    p,
+                    crate::effects::preset_names().join(", ")
+                ));
+            }
+            vec![format!("eq={}", p)]
+        }
+        Request::Effects { preset: None } => vec!["e".to_string()],
+        Request::Speed { value: Some(v), .. } => vec![format!("speed={}", v)],
+        Request::Speed {
+            delta: Some(d), ..
+        } => vec![format!("speed={}", crate::tempo::speed() + d)],
+        Request::Speed { .. } => return Err("speed needs 'value' or 'delta'".to_string()),
+        Request::Pitch { value: Some(v), .. } => vec![format!("pitch={}", v)],
+        Request::Pitch {
+            delta: Some(d), ..
+        } => vec![format!("pitch={}", crate::tempo::pitch() + d)],
+        Request::Pitch { .. } => return Err("pitch needs 'value' or 'delta'".to_string()),
+        Request::Sleep { spec } => {
+            crate::timer::parse_sleep(&spec)?;
+            vec![format!("sleep={}", spec)]
+        }
+        Request::Alarm { spec } => {
+            if let Some((_, crate::timer::AlarmTarget::Playlist(_))) =
+                crate::timer::parse_alarm(&spec)?
+                && config().offline_mode
+            {
+                return Err("playlists need online mode".to_string());
+            }
+            vec![format!("alarm={}", spec)]
+        }
+        Request::Device { name: Some(name) } => {
+            if !crate::output::devices().iter().any(|(n, _)| *n == name) {
+                return Err(format!("no audio device '{}' (see 'whytui ctl device')", name));
+            }
+            vec![format!("device={}", name)]
+        }
+        Request::Device { name: None } | Request::Status => Vec::new(),
+        Request::Launch { args } => launch_commands(&args)?,
+    };
+    Ok(cmds)
+}
+
+// another `whytui ...` started while we run and forwarded its arguments
+fn launch_commands(args: &[String]) -> Result<Vec<String>, String> {
+    let mut argv = vec!["whytui".to_string()];
+    argv.extend(args.iter().cloned());
+    match crate::cli::parse_args(&argv)?.command {
+        Command::Tui => Ok(Vec::new()),
+        Command::Play { target } => match crate::cli::extract_video_id(&target) {
+            Some(id) if !config().offline_mode => Ok(vec![format!("play_id={}", id)]),
+            Some(_) => Err("can't play from youtube in offline mode".to_string()),
+            None => Err(format!("only youtube ids and links can be forwarded, not '{}'", target)),
+        },
+        _ => Err("only a plain launch or 'play' can be forwarded".to_string()),
+    }
+}
+
+fn handle_line(line: &str, tx: &Sender<String>) -> Value {
+    let req = match serde_json::from_str::<Request>(line) {
+        Ok(r) => r,
+        Err(e) => return json!({"ok": false, "error": format!("bad request: {}", e)}),
+    };
+    if matches!(req, Request::Status) {
+        return json!({"ok": true, "status": status()});
+    }
+    if matches!(req, Request::Device { name: None }) {
+        let current = crate::output::current();
+        let devices: Vec<Value> = crate::output::devices()
+            .into_iter()
+            .map(|(name, description)| {
+                json!({"name": name, "description": description, "current": name == current})
+            })
+            .collect();
+        return json!({"ok": true, "devices": devices});
+    }
+    if matches!(req, Request::Enqueue { .. }) && config().offline_mode {
+        return json!({"ok": false, "error": "can't enqueue from youtube in offline mode"});
+    }
+    let launch = matches!(req, Request::Launch { .. });
+    match to_commands(req) {
+        // a plain forwarded launch, nothing to do besides saying we exist
+        Ok(cmds) if launch && cmds.is_empty() => json!({
+            "ok": true,
+            "message": format!("whytui is already running (pid {})", std::process::id()),
+        }),
+        Ok(cmds) => {
+            for cmd in cmds {
+                if tx.send(cmd).is_err() {
+                    return json!({"ok": false, "error": "player is shutting down"});
+                }
+            }
+            json!({"ok": true})
+        }
+        Err(e) => json!({"ok": false, "error": e}),
+    }
+}
+
+// ---- SERVER ----
+
+#[cfg(unix)]
+pub fn spawn(tx: Sender<String>) {
+    use std::os::unix::net::{UnixListener, UnixStream};
+
+    if !config().remote.control_socket {
+        return;
+    }
+    let path = socket_path();
+    // a stale socket from a crashed run would make bind fail
+    if UnixStream::connect(&path).is_err() {
+        let _ = std::fs::remove_file(&path);
+    }
+    let Ok(listener) = UnixListener::bind(&path) else {
+        return;
+    };
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let tx = tx.clone();
+            std::thread::spawn(move || {
+                let mut writer = match stream.try_clone() {
+                    Ok(w) => w,
+                    Err(_) => return,
+                };
+                for line in BufReader::new(stream).lines().map_while(Result::ok) {
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    let resp = handle_line(&line, &tx);
+                    if writeln!(writer, "{}", resp).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+    });
+}
+
+#[cfg(not(unix))]
+pub fn spawn(_tx: Sender<String>) {}
+
+// called on quit so the next launch doesn't find a dead socket
+pub fn cleanup() {
+    #[cfg(unix)]
+    if config().remote.control_socket {
+        let _ = std::fs::remove_file(socket_path());
+    }
+}
+
+// ---- CLIENT (whytui ctl) ----
+
+// `whytui ctl` arguments -> request
+pub fn parse_request(args: &[String]) -> Result<Request, String> {
+    let (cmd, rest) = args.split_first().ok_or("'ctl' needs a command")?;
+    let rest_joined = rest.join(" ");
+    let number = |what: &str| -> Result<i64, String> {
+        rest.first()
+            .ok_or(format!("'ctl {}' needs a number", what))?
+            .trim_start_matches('+')
+            .parse::<i64>()
+            .map_err(|_| format!("'ctl {}' expects a number, got '{}'", what, rest_joined))
+    };
+
+    let req = match cmd.as_str() {
+        "play-pause" | "toggle" => Request::PlayPause,
+        "play" => Request::Play,
+        "pause" => Request::Pause,
+        "next" => Request::Next,
+        "previous" | "prev" => Request::Previous,
+        "seek" => Request::Seek {
+            seconds: number("seek")?,
+        },
+        "volume" | "vol" => {
+            // +5 / -5 are relative, a bare number is absolute
+            let relative = rest.first().is_some_and(|v| v.starts_with(['+', '-']));
+            let n = number("volume")?;
+            if relative {
+                Request::Volume {
+                    value: None,
+                    delta: Some(n),
+                }
+            } else {
+                Request::Volume {
+                    value: Some(n),
+                    delta: None,
+                }
+            }
+        }
+        "enqueue" | "queue" => {
+            if rest_joined.trim().is_empty() {
+                return Err("'ctl enqueue' needs a search query or id:<videoId>".to_string());
+            }
+            match rest_joined.trim().strip_prefix("id:") {
+                Some(id) => Request::Enqueue {
+                    query: None,
+                    video_id: Some(id.to_string()),
+                },
+                None => Request::Enqueue {
+                    query: Some(rest_joined.trim().to_string()),
+                    video_id: None,
+                },
+            }
+        }
+        "like" => Request::Like,
+        "eq" | "effects" => Request::Effects {
+            preset: rest.first().cloned(),
+        },
+        "speed" => {
+            let relative = rest.first().is_some_and(|v| v.starts_with(['+', '-']));
+            let n = rest
+                .first()
+                .ok_or("'ctl speed' needs a number")?
+                .trim_start_matches('+')
+                .trim_end_matches('x')
+                .parse::<f64>()
+                .map_err(|_| format!("'ctl speed' expects a number, got '{}'", rest_joined))?;
+            if relative {
+                Request::Speed {
+                    value: None,
+                    delta: Some(n),
+                }
+            } else {
+                Request::Speed {
+                    value: Some(n),
+                    delta: None,
+                }
+            }
+        }
+        "pitch" => {
+            let relative = rest.first().is_some_and(|v| v.starts_with(['+', '-']));
+            let n = number("pitch")?;
+            if relative {
+                Request::Pitch {
+                    value: None,
+                    delta: Some(n),
+                }
+            } else {
+                Request::Pitch {
+                    value: Some(n),
+                    delta: None,
+                }
+            }
+        }
+        "sleep" => {
+            crate::timer::parse_sleep(&rest_joined)?;
+            Request::Sleep { spec: rest_joined }
+        }
+        "alarm" => {
+            crate::timer::parse_alarm(&rest_joined)?;
+            Request::Alarm { spec: rest_joined }
+        }
+        "device" | "devices" => Request::Device {
+            name: rest.first().cloned(),
+        },
+        "status" => Request::Status,
+        other => return Err(format!("unknown ctl command '{}'", other)),
+    };
+    Ok(req)
+}
+
+#[cfg(unix)]
+pub fn send_request(req: &Request) -> Result<Value, Box<dyn std::error::Error>> {
+    use std::os::unix::net::UnixStream;
+    use std::time::Duration;
+
+    let path = crate::instance::primary_ctl_socket().ok_or("no running whytui")?;
+    let mut stream = UnixStream::connect(&path)
+        .map_err(|_| format!("no running whytui at {}", path.display()))?;
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    writeln!(stream, "{}", serde_json::to_string(req)?)?;
+
+    let mut resp = String::new();
+    BufReader::new(&stream).read_line(&mut resp)?;
+    Ok(serde_json::from_str(&resp)?)
+}
+
+#[cfg(not(unix))]
+pub fn send_request(_req: &Request) -> Result<Value, Box<dyn std::error::Error>> {
+    Err("'ctl' needs unix sockets, not supported on this platform".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(line: &str) -> Result<Request, String> {
+        let args: Vec<String> = line.split_whitespace().map(String::from).collect();
+        parse_request(&args)
+    }
+
+    #[test]
+    fn parses_plain_commands() {
+        assert!(matches!(parse("toggle"), Ok(Request::PlayPause)));
+        assert!(matches!(parse("next"), Ok(Request::Next)));
+        assert!(matches!(parse("prev"), Ok(Request::Previous)));
+        assert!(matches!(parse("status"), Ok(Request::Status)));
+        assert!(matches!(parse("devices"), Ok(Request::Device { name: None })));
+        assert!(matches!(parse("eq"), Ok(Request::Effects { preset: None })));
+    }
+
+    #[test]
+    fn signed_numbers_are_relative() {
+        assert!(matches!(parse("seek -10"), Ok(Request::Seek { seconds: -10 })));
+        assert!(matches!(
+            parse("volume 60"),
+            Ok(Request::Volume {
+                value: Some(60),
+                delta: None
+            })
+        ));
+        assert!(matches!(
+            parse("vol +5"),
+            Ok(Request::Volume {
+                value: None,
+                delta: Some(5)
+            })
+        ));
+        assert!(matches!(
+            parse("pitch -2"),
+            Ok(Request::Pitch {
+                value: None,
+                delta: Some(-2)
+            })
+        ));
+        match parse("speed 1.5x") {
+            Ok(Request::Speed {
+                value: Some(v),
+                delta: None,
+            }) => assert_eq!(v, 1.5),
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn enqueue_takes_query_or_id() {
+        match parse("enqueue some song") {
+            Ok(Request::Enqueue {
+                query: Some(q),
+                video_id: None,
+            }) => assert_eq!(q, "some song"),
+            other => panic!("{:?}", other),
+        }
+        match parse("queue id:dQw4w9WgXcQ") {
+            Ok(Request::Enqueue {
+                query: None,
+                video_id: Some(id),
+            }) => assert_eq!(id, "dQw4w9WgXcQ"),
+            other => panic!("{:?}", other),
+        }
+        assert!(parse("enqueue").is_err());
+    }
+
+    #[test]
+    fn rejects_bad_input() {
+        assert!(parse("").is_err());
+        assert!(parse("rewind").is_err());
+        assert!(parse("seek").is_err());
+        assert!(parse("seek soon").is_err());
+        assert!(parse("volume loud").is_err());
+        assert!(parse("speed fast").is_err());
+    }
+
+    #[test]
+    fn requests_round_trip_as_json() {
+        let line = serde_json::to_string(&parse("vol +5").unwrap()).unwrap();
+        assert_eq!(line, r#"{"command":"volume","delta":5}"#);
+        assert!(matches!(
+            serde_json::from_str::<Request>(r#"{"command":"play-pause"}"#),
+            Ok(Request::PlayPause)
+        ));
+        assert!(serde_json::from_str::<Request>(r#"{"command":"seek","seconds":1,"x":1}"#).is_err());
+    }
+}
@@ -0,0 +1,143 @@
+use crate::settings::EffectPreset;
+use crate::{config, player};
+use std::sync::RwLock;
+
+// -------------------------------------------------------------------
+// 10 band equalizer + effect presets, turned into an ffmpeg filter graph
+// that mpv runs as the labelled audio filter @eq. the backend keeps it
+// across tracks and mpv restarts, the picked preset is saved in
+// config/effect_preset for the next launch
+// -------------------------------------------------------------------
+
+pub const BANDS: [u32; 10] = [31, 62, 125, 250, 500, 1000, 2000, 4000, 8000, 16000];
+
+const FILTER_LABEL: &str = "eq";
+
+// name, band gains, extra filters
+const BUILTIN: &[(&str, [f64; 10], &str)] = &[
+    ("flat", [0.0; 10], ""),
+    (
+        "bass",
+        [6.0, 5.0, 4.0, 2.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+        "",
+    ),
+    (
+        "vocal",
+        [-2.0, -2.0, -1.0, 0.0, 2.0, 4.0, 4.0, 2.0, 0.0, -1.0],
+        "",
+    ),
+    (
+        "loudness",
+        [6.0, 4.0, 2.0, 0.0, -1.0, -1.0, 0.0, 2.0, 4.0, 5.0],
+        "",
+    ),
+    // quiet parts up, loud parts down, less boom through the walls
+    (
+        "night",
+        [-3.0, -2.0, -1.0, 0.0, 0.0, 1.0, 1.0, 0.0, -1.0, -2.0],
+        "acompressor=threshold=0.05:ratio=6:attack=5:release=300:makeup=4",
+    ),
+];
+
+static CURRENT: RwLock<String> = RwLock::new(String::new());
+
+pub fn is_builtin(name: &str) -> bool {
+    BUILTIN.iter().any(|(n, _, _)| *n == name)
+}
+
+// built-ins first, then the config ones in name order
+pub fn preset_names() -> Vec<String> {
+    let mut names: Vec<String> = BUILTIN.iter().map(|(n, _, _)| n.to_string()).collect();
+    for name in config().effects.presets.keys() {
+        if !names.contains(name) {
+            names.push(name.clone());
+        }
+    }
+    names
+}
+
+fn lookup(name: &str) -> Option<EffectPreset> {
+    if let Some(p) = config().effects.presets.get(name) {
+        return Some(p.clone());
+    }
+    BUILTIN
+        .iter()
+        .find(|(n, _, _)| *n == name)
+        .map(|(_, bands, filters)| EffectPreset {
+            bands: bands.to_vec(),
+            filters: filters.to_string(),
+        })
+}
+
+// one octave wide peaking filter per band that isn't 0 dB, None = nothing to do
+fn filter_graph(preset: &EffectPreset) -> Option<String> {
+    let mut parts: Vec<String> = preset
+        .bands
+        .iter()
+        .zip(BANDS)
+        .filter(|(gain, _)| **gain != 0.0)
+        .map(|(gain, freq)| format!("equalizer=f={}:t=o:w=1:g={}", freq, gain))
+        .collect();
+    if !preset.filters.trim().is_empty() {
+        parts.push(preset.filters.trim().to_string());
+    }
+    (!parts.is_empty()).then(|| parts.join(","))
+}
+
+fn saved_path() -> Option<std::path::PathBuf> {
+    Some(crate::settings::config_dir()?.join("effect_preset"))
+}
+
+pub fn current() -> String {
+    CURRENT.read().unwrap().clone()
+}
+
+// called once on startup, before anything plays
+pub fn init() {
+    let saved = saved_path()
+        .and_then(|p| std::fs::read_to_string(p).ok())
+        .map(|s| s.trim().to_string())
+        .filter(|s| lookup(s).is_some());
+    let name = saved.unwrap_or_else(|| config().effects.preset.clone());
+    apply(&name);
+}
+
+fn apply(name: &str) {
+    let filter = lookup(name)
+        .and_then(|p| filter_graph(&p))
+        .map(|g| format!("lavfi=[{}]", g));
+    player::set_filter(FILTER_LABEL, filter.as_deref());
+    *CURRENT.write().unwrap() = name.to_string();
+}
+
+pub fn set(name: &str) -> Result<(), String> {
+    if lookup(name).is_none() {
+        return Err(format!(
+            "unknown preset '{}' (known: {})",
+            name,
+            preset_names().join(", ")
+        ));
+    }
+    apply(name);
+    if let Some(path) = saved_path() {
+        let _ = std::fs::write(path, name);
+    }
+    Ok(())
+}
+
+// next preset in [effects] cycle, returns its name
+pub fn cycle() -> String {
+    let order = if config().effects.cycle.is_empty() {
+        preset_names()
+    } else {
+        config().effects.cycle.clone()
+    };
+    let now = current();
+    let next = order
+        .iter()
+        .position(|n| *n == now)
+        .map(|i| order[(i + 1) % order.len()].clone())
+        .unwrap_or_else(|| order[0].clone());
+    let _ = set(&next);
+    next
+}
@@ -0,0 +1,186 @@
+use crate::Track;
+use crate::ui_common::duration_to_seconds;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// -------------------------------------------------------------------
+// why a track stopped when it shouldn't have. mpv giving up right after
+// a load (bad url, missing codec, broken dash manifest), a refused or
+// cut off stream and mpv itself dying all used to look like a song that
+// simply finished. here they get a reason; the main loop then plays the
+// track from its next source (resolve.rs), and tracks that keep failing
+// on every source are marked and skipped for the rest of the session
+// -------------------------------------------------------------------
+
+// ended this soon after loading = never really played
+const IMMEDIATE: Duration = Duration::from_secs(3);
+// a stream ending this much before its length didn't end on its own
+const EARLY_END_SECS: f64 = 10.0;
+// a track that ran out of sources this often is skipped when it comes up
+const MAX_FAILURES: u32 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Failure {
+    // http 403/404/410: expired or refused link
+    Refused,
+    // wrong url, format, codec or manifest
+    Unplayable,
+    // mpv itself died
+    Crashed,
+    // stopped well short of its length without saying why
+    CutOff,
+}
+
+impl Failure {
+    pub fn reason(&self) -> &'static str {
+        match self {
+            Failure::Refused => "stream refused",
+            Failure::Unplayable => "can't play this source",
+            Failure::Crashed => "player crashed",
+            Failure::CutOff => "stream cut off",
+        }
+    }
+
+    // a fresh url from the same source may well do
+    pub fn is_transient(&self) -> bool {
+        matches!(self, Failure::Refused | Failure::CutOff)
+    }
+}
+
+// when the backend last started a track
+static STARTED: Mutex<Option<Instant>> = Mutex::new(None);
+// tracks that failed on every source, by video id (or path): how often and why
+static MARKED: Mutex<BTreeMap<String, (u32, String)>> = Mutex::new(BTreeMap::new());
+
+fn key(track: &Track) -> String {
+    track.video_id.clone().unwrap_or_else(|| track.url.clone())
+}
+
+// called by player whenever a track starts
+pub fn note_started() {
+    *STARTED.lock().unwrap() = Some(Instant::now());
+}
+
+// None when `track` ended the way a song should. `error` is what the
+// backend said (mpv's file_error, or its exit status when it died),
+// `position` the last (position, duration) it reported before stopping
+pub fn classify(
+    track: &Track,
+    error: Option<&str>,
+    position: Option<(f64, f64)>,
+) -> Option<Failure> {
+    if let Some(e) = error {
+        let e = e.to_lowercase();
+        return Some(if e.starts_with("mpv exited") || e.starts_with("mpv quit") {
+            Failure::Crashed
+        } else if ["403", "404", "410", "forbidden", "http error"]
+            .iter()
+            .any(|k| e.contains(k))
+        {
+            Failure::Refused
+        } else {
+            Failure::Unplayable
+        });
+    }
+
+    let quick = STARTED
+        .lock()
+        .unwrap()
+        .is_some_and(|at| at.elapsed() < IMMEDIATE);
+    let (position, duration) = position.unwrap_or((0.0, 0.0));
+    if quick && position < 1.0 {
+        return Some(Failure::Unplayable);
+    }
+    // played to the stream's own end, youtube's length was just off
+    if duration > 0.0 && position >= duration - EARLY_END_SECS {
+        return None;
+    }
+    // local files are often a few seconds off the length youtube reports
+    let length = duration_to_seconds(&track.duration);
+    if crate::resolve::is_remote(track) && length > 0.0 && position < length - EARLY_END_SECS {
+        return Some(Failure::CutOff);
+    }
+    None
+}
+
+// "can't play this source (unrecognized file format)"
+pub fn describe(failure: Failure, error: Option<&str>) -> String {
+    match error {
+        Some(e) => format!("{} ({})", failure.reason(), crate::net::explain(e)),
+        None => failure.reason().to_string(),
+    }
+}
+
+// every source of `track` failed. the next time around the chain starts
+// over, unless it keeps failing
+pub fn mark(track: &Track, reason: &str) {
+    let mut marked = MARKED.lock().unwrap();
+    let entry = marked.entry(key(track)).or_insert((0, String::new()));
+    entry.0 += 1;
+    entry.1 = reason.to_string();
+    drop(marked);
+    crate::resolve::reset_chain(track);
+}
+
+// why `track` is skipped, None if it is still worth a try
+pub fn marked(track: &Track) -> Option<String> {
+    let marked = MARKED.lock().unwrap();
+    let (count, reason) = marked.get(&key(track))?;
+    (*count >= MAX_FAILURES).then(|| reason.clone())
+}
+
+// `track` played through, whatever failed before is forgotten
+pub fn clear(track: &Track) {
+    MARKED.lock().unwrap().remove(&key(track));
+    crate::resolve::reset_chain(track);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sorts_player_errors() {
+        let t = Track::sample(Some("abc"), "");
+        let failure = |e| classify(&t, Some(e), None);
+        assert_eq!(failure("mpv exited with exit status: 1"), Some(Failure::Crashed));
+        assert_eq!(failure("mpv quit"), Some(Failure::Crashed));
+        assert_eq!(failure("HTTP error 403 Forbidden"), Some(Failure::Refused));
+        assert_eq!(failure("http error 410 Gone"), Some(Failure::Refused));
+        assert_eq!(failure("unrecognized file format"), Some(Failure::Unplayable));
+        assert!(Failure::Refused.is_transient() && !Failure::Unplayable.is_transient());
+    }
+
+    // one test, they all share STARTED
+    #[test]
+    fn quiet_endings_by_timing_and_length() {
+        let stream = Track::sample(Some("abc"), "");
+        let local = Track::sample(Some("abc"), "/music/Song - Artist.opus");
+
+        note_started();
+        assert_eq!(classify(&stream, None, Some((0.2, 180.0))), Some(Failure::Unplayable));
+        assert_eq!(classify(&stream, None, None), Some(Failure::Unplayable));
+        // a resumed track may start near its end
+        assert_eq!(classify(&stream, None, Some((175.0, 180.0))), None);
+
+        *STARTED.lock().unwrap() = Some(Instant::now() - Duration::from_secs(60));
+        assert_eq!(classify(&stream, None, Some((100.0, 180.0))), Some(Failure::CutOff));
+        assert_eq!(classify(&stream, None, None), Some(Failure::CutOff));
+        assert_eq!(classify(&stream, None, Some((172.0, 0.0))), None);
+        // the stream is shorter than youtube says and played to its end
+        assert_eq!(classify(&stream, None, Some((149.8, 150.0))), None);
+        assert_eq!(classify(&local, None, Some((100.0, 180.0))), None);
+    }
+
+    #[test]
+    fn marks_tracks_that_keep_failing() {
+        let t = Track::sample(Some("marked-test"), "");
+        mark(&t, "stream refused");
+        assert_eq!(marked(&t), None);
+        mark(&t, "can't play this source");
+        assert_eq!(marked(&t).as_deref(), Some("can't play this source"));
+        clear(&t);
+        assert_eq!(marked(&t), None);
+    }
+}
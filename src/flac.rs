@@ -1,6 +1,5 @@
 use crate::config;
 use base64::prelude::*;
-use reqwest::Client;
 use serde_json::Value;
 use std::error::Error;
 use std::io::Write;
@@ -30,7 +29,7 @@ pub async fn init_api() -> Result<(), Box<dyn Error + Send + Sync>> {
         return Ok(());
     }
 
-    let client = Client::builder()
+    let client = crate::net::client_builder()
         .user_agent(UA)
         .timeout(std::time::Duration::from_secs(3))
         .build()?;
@@ -61,7 +60,7 @@ pub async fn fetch_flac_stream_url(
     target_duration: &str,
 ) -> Result<String, Box<dyn Error + Send + Sync>> {
     let api_base = ACTIVE_API.get().ok_or("API not initialized")?;
-    let client = Client::builder().user_agent(UA).build()?;
+    let client = crate::net::client_builder().user_agent(UA).build()?;
     let target_secs = parse_to_seconds(target_duration);
 
     let search_url = format!("{}/search/?s={}", api_base, urlencoding::encode(query));
@@ -147,11 +146,7 @@ fn decode_manifest(encoded: &str, track_id: i64) -> Result<String, Box<dyn Error
     }
 
     if decoded_bytes.first().map(|&b| b == b'<').unwrap_or(false) {
-        let mut path = dirs::audio_dir().ok_or("Could not find audio directory")?;
-        path.push("whytui");
-        path.push("temp");
-        std::fs::create_dir_all(&path).map_err(|e| e.to_string())?;
-        path.push(format!("{}.mpd", track_id));
+        let path = crate::instance::temp_dir().join(format!("{}.mpd", track_id));
         std::fs::write(&path, &decoded_bytes).map_err(|e| e.to_string())?;
 
         return Ok(path.to_string_lossy().to_string());
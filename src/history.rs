@@ -0,0 +1,193 @@
+use crate::Track;
+use serde::{Deserialize, Serialize};
+use std::fs::OpenOptions;
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::sync::RwLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// -------------------------------------------------------------------
+// history.jsonl in the config dir: one line per play, never rewritten
+// (RECENTLY_PLAYED stays the small in-memory list used by `p`)
+// -------------------------------------------------------------------
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PlaySource {
+    Cache,
+    Youtube,
+    Lossless,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub timestamp: u64,
+    pub title: String,
+    pub artists: Vec<String>,
+    pub album: String,
+    pub duration: String,
+    pub video_id: Option<String>,
+    pub path: Option<String>,
+    pub thumbnail_url: Option<String>,
+    pub source: PlaySource,
+    // PLAYING_LOSSLESS at start, also true for cached .flac files
+    #[serde(default)]
+    pub lossless: bool,
+    pub seconds_listened: f64,
+    pub skipped: bool,
+}
+
+struct PlayState {
+    track: Track,
+    started_at: u64,
+    source: PlaySource,
+    // the file it played from, for cached plays
+    path: Option<String>,
+    lossless: bool,
+    last_pos: Option<f64>,
+    listened: f64,
+}
+
+static CURRENT_PLAY: RwLock<Option<PlayState>> = RwLock::new(None);
+
+fn history_path() -> Option<PathBuf> {
+    Some(crate::settings::config_dir()?.join("history.jsonl"))
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+pub fn source_of(url: &str) -> PlaySource {
+    let remote = url.starts_with("http");
+    if !remote && !url.ends_with(".mpd") {
+        PlaySource::Cache
+    } else if url.contains(".tidal") || url.ends_with(".flac") || url.ends_with(".mpd") {
+        PlaySource::Lossless
+    } else {
+        PlaySource::Youtube
+    }
+}
+
+// called by player whenever mpv starts a track (after PLAYING_LOSSLESS is set).
+// a track restarted on a fresh stream url (no finish in between) stays one play
+pub fn begin(track: &Track, source: &str) {
+    let lossless = crate::PLAYING_LOSSLESS.load(std::sync::atomic::Ordering::SeqCst);
+    let mut guard = CURRENT_PLAY.write().unwrap();
+    if let Some(state) = guard.as_mut()
+        && state.track == *track
+    {
+        state.source = source_of(source);
+        state.path = (state.source == PlaySource::Cache).then(|| source.to_string());
+        state.lossless = lossless;
+        state.last_pos = None;
+        return;
+    }
+    crate::scrobble::now_playing(track);
+    let source_kind = source_of(source);
+    *guard = Some(PlayState {
+        track: track.clone(),
+        started_at: now_secs(),
+        source: source_kind,
+        path: (source_kind == PlaySource::Cache).then(|| source.to_string()),
+        lossless,
+        last_pos: None,
+        listened: 0.0,
+    });
+}
+
+pub fn current_track() -> Option<Track> {
+    CURRENT_PLAY.read().unwrap().as_ref().map(|s| s.track.clone())
+}
+
+// fed from the ui monitor thread; small forward steps count as listening,
+// pauses (no change) and seeks (big jumps) don't. None and 0.0 are mpv
+// between files, not a position
+pub fn note_position(pos: Option<f64>) {
+    let Some(pos) = pos.filter(|p| *p > 0.0) else {
+        return;
+    };
+    let mut guard = CURRENT_PLAY.write().unwrap();
+    if let Some(state) = guard.as_mut() {
+        if let Some(last) = state.last_pos {
+            let delta = pos - last;
+            if delta > 0.0 && delta < 2.0 {
+                state.listened += delta;
+            }
+        }
+        state.last_pos = Some(pos);
+    }
+}
+
+// closes the current play, appends it to history.jsonl and hands it to the scrobblers
+pub fn finish(skipped: bool) -> Option<HistoryEntry> {
+    let state = CURRENT_PLAY.write().unwrap().take()?;
+    let track = state.track;
+
+    let path = state.path;
+    let entry = HistoryEntry {
+        timestamp: state.started_at,
+        title: track.title,
+        artists: track.artists,
+        album: track.album,
+        duration: track.duration,
+        video_id: track.video_id,
+        path,
+        thumbnail_url: track.thumbnail_url,
+        source: state.source,
+        lossless: state.lossless,
+        seconds_listened: (state.listened * 10.0).round() / 10.0,
+        skipped,
+    };
+
+    if let Some(file_path) = history_path()
+        && let Ok(line) = serde_json::to_string(&entry)
+        && let Ok(mut file) = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(file_path)
+    {
+        let _ = writeln!(file, "{}", line);
+    }
+    crate::scrobble::scrobble(&entry);
+    Some(entry)
+}
+
+// oldest first, broken lines (e.g. from a crash mid-write) are skipped
+pub fn load_all() -> Vec<HistoryEntry> {
+    let Some(path) = history_path() else {
+        return Vec::new();
+    };
+    let Ok(file) = std::fs::File::open(path) else {
+        return Vec::new();
+    };
+    BufReader::new(file)
+        .lines()
+        .map_while(Result::ok)
+        .filter_map(|l| serde_json::from_str(&l).ok())
+        .collect()
+}
+
+impl HistoryEntry {
+    pub fn to_track(&self) -> Track {
+        Track::new(
+            self.title.clone(),
+            self.artists.clone(),
+            self.album.clone(),
+            self.duration.clone(),
+            self.thumbnail_url.clone(),
+            self.video_id.clone(),
+            self.path.clone().unwrap_or_default(),
+        )
+    }
+
+    pub fn local_file_exists(&self) -> bool {
+        self.path
+            .as_ref()
+            .map(|p| std::path::Path::new(p).exists())
+            .unwrap_or(false)
+    }
+}
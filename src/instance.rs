@@ -0,0 +1,205 @@
+use crate::config;
+use crate::settings::AlreadyRunning;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+// -------------------------------------------------------------------
+// every whytui gets its own dir under $XDG_RUNTIME_DIR/whytui/<pid> for
+// the mpv socket, the ctl socket and half finished downloads, so two
+// instances never touch each other's files. the first tui to start
+// holds whytui.lock and is the one `whytui ctl` (and forwarding) talks to
+// -------------------------------------------------------------------
+
+const PRIMARY_LOCK: &str = "whytui.lock";
+const INSTANCE_LOCK: &str = "lock";
+
+// kept open for the whole run, the os drops the locks when we die
+static INSTANCE: OnceLock<(PathBuf, Option<File>)> = OnceLock::new();
+static PRIMARY: OnceLock<File> = OnceLock::new();
+
+pub fn runtime_dir() -> PathBuf {
+    match std::env::var_os("XDG_RUNTIME_DIR").filter(|d| !d.is_empty()) {
+        Some(d) => PathBuf::from(d).join("whytui"),
+        // no runtime dir (macos, windows, bare ttys): per user dir in tmp
+        None => {
+            let user = std::env::var("USER")
+                .or_else(|_| std::env::var("USERNAME"))
+                .unwrap_or_else(|_| "user".to_string());
+            std::env::temp_dir().join(format!("whytui-{}", user))
+        }
+    }
+}
+
+fn create_private_dir(dir: &Path) {
+    let _ = fs::create_dir_all(dir);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let _ = fs::set_permissions(dir, fs::Permissions::from_mode(0o700));
+    }
+}
+
+// created (and locked) on first use
+pub fn instance_dir() -> &'static Path {
+    let (dir, _) = INSTANCE.get_or_init(|| {
+        let root = runtime_dir();
+        create_private_dir(&root);
+        let dir = root.join(std::process::id().to_string());
+        create_private_dir(&dir);
+        let lock = File::create(dir.join(INSTANCE_LOCK))
+            .ok()
+            .filter(|f| f.try_lock().is_ok());
+        (dir, lock)
+    });
+    dir
+}
+
+// `n` tells apart the mpvs of one instance (a fading one and its successor)
+pub fn mpv_socket(n: u32) -> String {
+    if cfg!(unix) {
+        instance_dir()
+            .join(format!("mpv-{}.sock", n))
+            .to_string_lossy()
+            .to_string()
+    } else {
+        format!(r"\\.\pipe\whytui-{}-{}.sock", std::process::id(), n)
+    }
+}
+
+pub fn ctl_socket() -> PathBuf {
+    instance_dir().join("ctl.sock")
+}
+
+// downloads are written here and moved into the music dir when complete
+pub fn temp_dir() -> PathBuf {
+    let dir = instance_dir().join("temp");
+    let _ = fs::create_dir_all(&dir);
+    dir
+}
+
+// ---- PRIMARY INSTANCE ----
+
+// Ok if we are now the primary instance, Err(pid) of the one that already is
+pub fn claim_primary() -> Result<(), u32> {
+    let root = runtime_dir();
+    create_private_dir(&root);
+    // no truncate here, that would wipe the running instance's pid
+    let Ok(mut file) = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(root.join(PRIMARY_LOCK))
+    else {
+        // can't lock anything, behave like a lone instance
+        return Ok(());
+    };
+    if file.try_lock().is_err() {
+        return Err(read_pid(&mut file).unwrap_or(0));
+    }
+    let _ = file.set_len(0);
+    let _ = file.rewind();
+    let _ = write!(file, "{}", std::process::id());
+    let _ = file.flush();
+    let _ = PRIMARY.set(file);
+    Ok(())
+}
+
+fn read_pid(file: &mut File) -> Option<u32> {
+    let mut s = String::new();
+    file.read_to_string(&mut s).ok()?;
+    s.trim().parse().ok()
+}
+
+// pid of the running primary instance, None if nobody holds the lock
+pub fn primary_pid() -> Option<u32> {
+    let mut file = File::open(runtime_dir().join(PRIMARY_LOCK)).ok()?;
+    if file.try_lock_shared().is_ok() {
+        return None;
+    }
+    read_pid(&mut file)
+}
+
+pub fn primary_ctl_socket() -> Option<PathBuf> {
+    let pid = primary_pid()?;
+    Some(runtime_dir().join(pid.to_string()).join("ctl.sock"))
+}
+
+// what to do when a tui (or `whytui play`) starts next to a running tui.
+// returns true when this process should exit right away
+pub fn handle_already_running(args: &[String], claim: bool) -> bool {
+    let running = if claim {
+        claim_primary().err()
+    } else {
+        primary_pid()
+    };
+    let Some(pid) = running else {
+        return false;
+    };
+    match config().remote.already_running {
+        AlreadyRunning::Allow => false,
+        AlreadyRunning::Refuse => {
+            eprintln!("whytui is already running (pid {})", pid);
+            std::process::exit(1);
+        }
+        AlreadyRunning::Forward => {
+            let req = crate::control::Request::Launch {
+                args: args.iter().skip(1).cloned().collect(),
+            };
+            match crate::control::send_request(&req) {
+                Ok(resp) if resp["ok"].as_bool() == Some(true) => {
+                    match resp["message"].as_str() {
+                        Some(msg) => println!("{}", msg),
+                        None => println!("sent to the running whytui (pid {})", pid),
+                    }
+                    true
+                }
+                Ok(resp) => {
+                    eprintln!(
+                        "whytui: {}",
+                        resp["error"].as_str().unwrap_or("request failed")
+                    );
+                    std::process::exit(1);
+                }
+                Err(e) => {
+                    eprintln!("whytui is already running (pid {}): {}", pid, e);
+                    std::process::exit(1);
+                }
+            }
+        }
+    }
+}
+
+// ---- CLEANUP ----
+
+// removes the dirs of instances that crashed or were killed. a dir whose
+// lock can still be taken has nobody behind it
+pub fn clear_stale() {
+    let me = std::process::id().to_string();
+    let Ok(entries) = fs::read_dir(runtime_dir()) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if !path.is_dir() || entry.file_name().to_string_lossy() == me {
+            continue;
+        }
+        let stale = match File::open(path.join(INSTANCE_LOCK)) {
+            Ok(f) => f.try_lock().is_ok(),
+            // still starting up (or not ours), leave it alone
+            Err(_) => false,
+        };
+        if stale {
+            let _ = fs::remove_dir_all(&path);
+        }
+    }
+}
+
+// called on quit
+pub fn release() {
+    if let Some((dir, _)) = INSTANCE.get() {
+        let _ = fs::remove_dir_all(dir);
+    }
+}
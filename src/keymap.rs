@@ -0,0 +1,561 @@
+use crate::config;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MediaKeyCode};
+use std::collections::HashMap;
+use std::fs;
+use std::sync::OnceLock;
+
+// -------------------------------------------------------------------
+// keymap.toml maps a command name to one or more key chords, e.g.
+//     next = ["n", "ctrl+right", "media_next"]
+// commands missing from the file keep their default chords
+// -------------------------------------------------------------------
+
+static KEYMAP: OnceLock<Keymap> = OnceLock::new();
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    Search,
+    Refresh,
+    Pause,
+    Next,
+    Previous,
+    Library,
+    Like,
+    AddToPlaylist,
+    User,
+    View,
+    Translate,
+    Recents,
+    Stats,
+    Repeat,
+    Guess,
+    ClearQueue,
+    Effects,
+    SpeedUp,
+    SpeedDown,
+    SpeedReset,
+    PitchUp,
+    PitchDown,
+    SleepTimer,
+    AudioDevice,
+    VolumeUp,
+    VolumeDown,
+    LyricOffsetUp,
+    LyricOffsetDown,
+    SeekForward,
+    SeekBackward,
+    Quit,
+    Queue1,
+    Queue2,
+    Queue3,
+    Queue4,
+    Queue5,
+    Queue6,
+    Queue7,
+    Queue8,
+    Queue9,
+}
+
+// name in keymap.toml, action, default chords
+const ACTIONS: &[(&str, Action, &[&str])] = &[
+    ("search", Action::Search, &["/"]),
+    ("refresh", Action::Refresh, &["esc"]),
+    (
+        "pause",
+        Action::Pause,
+        &["space", "media_play_pause", "media_play", "media_pause"],
+    ),
+    ("next", Action::Next, &["n", "media_next"]),
+    ("previous", Action::Previous, &["p", "media_previous"]),
+    ("library", Action::Library, &["L"]),
+    ("like", Action::Like, &["l"]),
+    ("add_to_playlist", Action::AddToPlaylist, &["a"]),
+    ("user", Action::User, &["u"]),
+    ("view", Action::View, &["v"]),
+    ("translate", Action::Translate, &["t"]),
+    ("recents", Action::Recents, &["r"]),
+    ("stats", Action::Stats, &["S"]),
+    ("repeat", Action::Repeat, &["R"]),
+    ("guess", Action::Guess, &["g"]),
+    ("clear_queue", Action::ClearQueue, &["c"]),
+    ("effects", Action::Effects, &["e"]),
+    ("speed_up", Action::SpeedUp, &["}"]),
+    ("speed_down", Action::SpeedDown, &["{"]),
+    ("speed_reset", Action::SpeedReset, &["\\"]),
+    ("pitch_up", Action::PitchUp, &[")"]),
+    ("pitch_down", Action::PitchDown, &["("]),
+    ("sleep_timer", Action::SleepTimer, &["z"]),
+    ("audio_device", Action::AudioDevice, &["o"]),
+    (
+        "volume_up",
+        Action::VolumeUp,
+        &["+", "=", "media_volume_up"],
+    ),
+    (
+        "volume_down",
+        Action::VolumeDown,
+        &["-", "media_volume_down"],
+    ),
+    ("lyric_offset_up", Action::LyricOffsetUp, &["]"]),
+    ("lyric_offset_down", Action::LyricOffsetDown, &["["]),
+    (
+        "seek_forward",
+        Action::SeekForward,
+        &["right", "media_fast_forward"],
+    ),
+    (
+        "seek_backward",
+        Action::SeekBackward,
+        &["left", "media_rewind"],
+    ),
+    ("quit", Action::Quit, &["q"]),
+    // shift+digit depends on the layout, alt+digit works everywhere
+    ("queue_1", Action::Queue1, &["!", "alt+1"]),
+    ("queue_2", Action::Queue2, &["@", "alt+2"]),
+    ("queue_3", Action::Queue3, &["#", "alt+3"]),
+    ("queue_4", Action::Queue4, &["$", "€", "alt+4"]),
+    ("queue_5", Action::Queue5, &["%", "alt+5"]),
+    // lists can be up to 9 long (ui.library_page_size)
+    ("queue_6", Action::Queue6, &["alt+6"]),
+    ("queue_7", Action::Queue7, &["alt+7"]),
+    ("queue_8", Action::Queue8, &["alt+8"]),
+    ("queue_9", Action::Queue9, &["alt+9"]),
+];
+
+impl Action {
+    pub fn name(self) -> &'static str {
+        ACTIONS
+            .iter()
+            .find(|(_, a, _)| *a == self)
+            .map(|(n, _, _)| *n)
+            .unwrap_or("unknown")
+    }
+
+    // the string handle_global_commands / the menus expect on rx
+    // (search and refresh are handled in the input thread itself)
+    pub fn command(self) -> Option<String> {
+        let cmd = match self {
+            Action::Search | Action::Refresh => return None,
+            Action::Pause => "pause".to_string(),
+            Action::Next => "n".to_string(),
+            Action::Previous => "p".to_string(),
+            Action::Library => "L".to_string(),
+            Action::Like => "l".to_string(),
+            Action::AddToPlaylist => "a".to_string(),
+            Action::User => "u".to_string(),
+            Action::View => "v".to_string(),
+            Action::Translate => "t".to_string(),
+            Action::Recents => "r".to_string(),
+            Action::Stats => "S".to_string(),
+            Action::Repeat => "R".to_string(),
+            Action::Guess => "g".to_string(),
+            Action::ClearQueue => "c".to_string(),
+            Action::Effects => "e".to_string(),
+            Action::SpeedUp => "speed+".to_string(),
+            Action::SpeedDown => "speed-".to_string(),
+            Action::SpeedReset => "speed=1".to_string(),
+            Action::PitchUp => "pitch+".to_string(),
+            Action::PitchDown => "pitch-".to_string(),
+            Action::SleepTimer => "z".to_string(),
+            Action::AudioDevice => "o".to_string(),
+            Action::VolumeUp => "+".to_string(),
+            Action::VolumeDown => "-".to_string(),
+            Action::LyricOffsetUp => "]".to_string(),
+            Action::LyricOffsetDown => "[".to_string(),
+            Action::SeekForward => format!(">{}", config().playback.seek_seconds),
+            Action::SeekBackward => format!("<{}", config().playback.seek_seconds),
+            Action::Quit => "q".to_string(),
+            Action::Queue1 => "q1".to_string(),
+            Action::Queue2 => "q2".to_string(),
+            Action::Queue3 => "q3".to_string(),
+            Action::Queue4 => "q4".to_string(),
+            Action::Queue5 => "q5".to_string(),
+            Action::Queue6 => "q6".to_string(),
+            Action::Queue7 => "q7".to_string(),
+            Action::Queue8 => "q8".to_string(),
+            Action::Queue9 => "q9".to_string(),
+        };
+        Some(cmd)
+    }
+}
+
+pub type KeyChord = (KeyCode, KeyModifiers);
+
+pub struct Keymap {
+    bindings: HashMap<KeyChord, Action>,
+}
+
+impl Keymap {
+    pub fn lookup(&self, key: &KeyEvent) -> Option<Action> {
+        self.bindings
+            .get(&normalize(key.code, key.modifiers))
+            .copied()
+    }
+}
+
+pub fn keymap() -> &'static Keymap {
+    KEYMAP.get_or_init(|| build_keymap(&HashMap::new()).expect("default keymap is valid"))
+}
+
+const DEFAULT_KEYMAP: &str = r##"# whytui keymap
+# command = ["chord", ...]  (a listed command replaces all of its default chords)
+# modifiers: ctrl, alt, shift, super   e.g. "ctrl+n", "alt+1", "shift+left"
+# keys: any character, space, enter, esc, tab, backspace, delete, insert, home, end,
+#       pageup, pagedown, up, down, left, right, f1-f24,
+#       media_play_pause, media_play, media_pause, media_stop, media_next, media_previous,
+#       media_fast_forward, media_rewind, media_volume_up, media_volume_down, media_mute
+# media keys only arrive on terminals with the kitty keyboard protocol
+# plain digits are reserved for picking songs from lists
+
+# search = ["/"]
+# refresh = ["esc"]
+# pause = ["space", "media_play_pause", "media_play", "media_pause"]
+# next = ["n", "media_next"]
+# previous = ["p", "media_previous"]
+# library = ["L"]
+# like = ["l"]
+# add_to_playlist = ["a"]
+# user = ["u"]
+# view = ["v"]
+# translate = ["t"]
+# recents = ["r"]
+# stats = ["S"]
+# repeat = ["R"]
+# guess = ["g"]
+# clear_queue = ["c"]
+# effects = ["e"]
+# speed_up = ["}"]
+# speed_down = ["{"]
+# speed_reset = ["\\"]
+# pitch_up = [")"]
+# pitch_down = ["("]
+# sleep_timer = ["z"]
+# audio_device = ["o"]
+# volume_up = ["+", "=", "media_volume_up"]
+# volume_down = ["-", "media_volume_down"]
+# lyric_offset_up = ["]"]
+# lyric_offset_down = ["["]
+# seek_forward = ["right", "media_fast_forward"]
+# seek_backward = ["left", "media_rewind"]
+# quit = ["q"]
+# queue_1 = ["!", "alt+1"]
+# queue_2 = ["@", "alt+2"]
+# queue_3 = ["#", "alt+3"]
+# queue_4 = ["$", "€", "alt+4"]
+# queue_5 = ["%", "alt+5"]
+# queue_6 = ["alt+6"]
+# queue_7 = ["alt+7"]
+# queue_8 = ["alt+8"]
+# queue_9 = ["alt+9"]
+"##;
+
+pub fn load_keymap() -> Result<(), String> {
+    let dir = crate::settings::config_dir().ok_or("No audio dir")?;
+    let path = dir.join("keymap.toml");
+
+    let overrides = if path.exists() {
+        let raw = fs::read_to_string(&path)
+            .map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+        parse_overrides(&raw).map_err(|e| format!("Invalid {}:\n  - {}", path.display(), e))?
+    } else {
+        let _ = fs::write(&path, DEFAULT_KEYMAP);
+        HashMap::new()
+    };
+
+    let map = build_keymap(&overrides).map_err(|problems| {
+        format!(
+            "Invalid {}:\n  - {}",
+            path.display(),
+            problems.join("\n  - ")
+        )
+    })?;
+    let _ = KEYMAP.set(map);
+    Ok(())
+}
+
+fn parse_overrides(raw: &str) -> Result<HashMap<String, Vec<String>>, String> {
+    let table: toml::Table = toml::from_str(raw).map_err(|e| e.to_string())?;
+    let mut out = HashMap::new();
+
+    for (name, value) in table {
+        if !ACTIONS.iter().any(|(n, _, _)| *n == name) {
+            let known: Vec<&str> = ACTIONS.iter().map(|(n, _, _)| *n).collect();
+            return Err(format!(
+                "unknown command '{}' (known: {})",
+                name,
+                known.join(", ")
+            ));
+        }
+        let chords = match value {
+            toml::Value::String(s) => vec![s],
+            toml::Value::Array(arr) => arr
+                .into_iter()
+                .map(|v| match v {
+                    toml::Value::String(s) => Ok(s),
+                    other => Err(format!(
+                        "'{}' expects key chords as strings, got {}",
+                        name, other
+                    )),
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+            other => {
+                return Err(format!(
+                    "'{}' expects a chord or a list of chords, got {}",
+                    name, other
+                ));
+            }
+        };
+        out.insert(name, chords);
+    }
+    Ok(out)
+}
+
+fn build_keymap(overrides: &HashMap<String, Vec<String>>) -> Result<Keymap, Vec<String>> {
+    let mut bindings: HashMap<KeyChord, Action> = HashMap::new();
+    let mut problems = Vec::new();
+
+    for (name, action, defaults) in ACTIONS {
+        let chords: Vec<&str> = match overrides.get(*name) {
+            Some(list) => list.iter().map(|s| s.as_str()).collect(),
+            None => defaults.to_vec(),
+        };
+
+        for chord_str in chords {
+            let chord = match parse_chord(chord_str) {
+                Ok(c) => c,
+                Err(e) => {
+                    problems.push(format!("{}: {}", name, e));
+                    continue;
+                }
+            };
+
+            if let (KeyCode::Char(c), m) = chord
+                && c.is_ascii_digit()
+                && m.is_empty()
+            {
+                problems.push(format!(
+                    "{}: '{}' is reserved for picking songs from lists",
+                    name, chord_str
+                ));
+                continue;
+            }
+
+            match bindings.get(&chord) {
+                Some(other) if other != action => problems.push(format!(
+                    "'{}' is bound to both '{}' and '{}'",
+                    chord_str,
+                    other.name(),
+                    name
+                )),
+                _ => {
+                    bindings.insert(chord, *action);
+                }
+            }
+        }
+    }
+
+    if problems.is_empty() {
+        Ok(Keymap { bindings })
+    } else {
+        Err(problems)
+    }
+}
+
+pub fn parse_chord(s: &str) -> Result<KeyChord, String> {
+    if s.is_empty() {
+        return Err("empty key chord".to_string());
+    }
+
+    // "+" and "ctrl++" name the plus key itself
+    let (mods_part, key_part) = if s == "+" {
+        ("", "+")
+    } else if let Some(prefix) = s.strip_suffix("++") {
+        (prefix, "+")
+    } else {
+        match s.rfind('+') {
+            Some(pos) => (&s[..pos], &s[pos + 1..]),
+            None => ("", s),
+        }
+    };
+
+    let mut modifiers = KeyModifiers::NONE;
+    for m in mods_part.split('+').filter(|m| !m.is_empty()) {
+        modifiers |= match m.to_lowercase().as_str() {
+            "ctrl" | "control" => KeyModifiers::CONTROL,
+            "alt" | "meta" | "option" => KeyModifiers::ALT,
+            "shift" => KeyModifiers::SHIFT,
+            "super" | "cmd" | "win" => KeyModifiers::SUPER,
+            other => return Err(format!("unknown modifier '{}' in '{}'", other, s)),
+        };
+    }
+
+    let code =
+        parse_key(key_part).ok_or_else(|| format!("unknown key '{}' in '{}'", key_part, s))?;
+    Ok(normalize(code, modifiers))
+}
+
+fn parse_key(k: &str) -> Option<KeyCode> {
+    let mut chars = k.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Some(KeyCode::Char(c));
+    }
+
+    let lower = k.to_lowercase();
+    if let Some(n) = lower.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+        return (1..=24).contains(&n).then_some(KeyCode::F(n));
+    }
+
+    Some(match lower.as_str() {
+        "space" => KeyCode::Char(' '),
+        "enter" | "return" => KeyCode::Enter,
+        "esc" | "escape" => KeyCode::Esc,
+        "tab" => KeyCode::Tab,
+        "backspace" => KeyCode::Backspace,
+        "delete" | "del" => KeyCode::Delete,
+        "insert" | "ins" => KeyCode::Insert,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "media_play_pause" => KeyCode::Media(MediaKeyCode::PlayPause),
+        "media_play" => KeyCode::Media(MediaKeyCode::Play),
+        "media_pause" => KeyCode::Media(MediaKeyCode::Pause),
+        "media_stop" => KeyCode::Media(MediaKeyCode::Stop),
+        "media_next" => KeyCode::Media(MediaKeyCode::TrackNext),
+        "media_previous" => KeyCode::Media(MediaKeyCode::TrackPrevious),
+        "media_fast_forward" => KeyCode::Media(MediaKeyCode::FastForward),
+        "media_rewind" => KeyCode::Media(MediaKeyCode::Rewind),
+        "media_volume_up" => KeyCode::Media(MediaKeyCode::RaiseVolume),
+        "media_volume_down" => KeyCode::Media(MediaKeyCode::LowerVolume),
+        "media_mute" => KeyCode::Media(MediaKeyCode::MuteVolume),
+        _ => return None,
+    })
+}
+
+// terminals send 'L' + SHIFT for shift+l, so fold shift into the character
+fn normalize(code: KeyCode, modifiers: KeyModifiers) -> KeyChord {
+    match code {
+        KeyCode::Char(c) if modifiers.contains(KeyModifiers::SHIFT) && c.is_alphabetic() => (
+            KeyCode::Char(c.to_uppercase().next().unwrap_or(c)),
+            modifiers - KeyModifiers::SHIFT,
+        ),
+        KeyCode::Char(c) if !c.is_alphanumeric() && c != ' ' => {
+            // symbols already carry the shift ("!" not shift+1)
+            (code, modifiers - KeyModifiers::SHIFT)
+        }
+        KeyCode::BackTab => (KeyCode::Tab, modifiers | KeyModifiers::SHIFT),
+        _ => (code, modifiers),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn overrides(pairs: &[(&str, &[&str])]) -> HashMap<String, Vec<String>> {
+        pairs
+            .iter()
+            .map(|(n, c)| (n.to_string(), c.iter().map(|s| s.to_string()).collect()))
+            .collect()
+    }
+
+    #[test]
+    fn parses_plain_and_modified_chords() {
+        assert_eq!(parse_chord("n"), Ok((KeyCode::Char('n'), KeyModifiers::NONE)));
+        assert_eq!(
+            parse_chord("ctrl+right"),
+            Ok((KeyCode::Right, KeyModifiers::CONTROL))
+        );
+        assert_eq!(
+            parse_chord("Alt+Shift+f5"),
+            Ok((KeyCode::F(5), KeyModifiers::ALT | KeyModifiers::SHIFT))
+        );
+        assert_eq!(
+            parse_chord("media_next"),
+            Ok((KeyCode::Media(MediaKeyCode::TrackNext), KeyModifiers::NONE))
+        );
+    }
+
+    #[test]
+    fn plus_key_can_be_bound() {
+        assert_eq!(parse_chord("+"), Ok((KeyCode::Char('+'), KeyModifiers::NONE)));
+        assert_eq!(
+            parse_chord("ctrl++"),
+            Ok((KeyCode::Char('+'), KeyModifiers::CONTROL))
+        );
+    }
+
+    #[test]
+    fn shift_folds_into_letters_and_symbols() {
+        assert_eq!(parse_chord("shift+l"), parse_chord("L"));
+        assert_eq!(parse_chord("shift+!"), parse_chord("!"));
+    }
+
+    #[test]
+    fn rejects_unknown_keys_and_modifiers() {
+        assert!(parse_chord("").is_err());
+        assert!(parse_chord("hyper+n").is_err());
+        assert!(parse_chord("f25").is_err());
+        assert!(parse_chord("ctrl+nope").is_err());
+    }
+
+    #[test]
+    fn default_keymap_is_valid() {
+        let map = build_keymap(&HashMap::new()).expect("defaults conflict");
+        let key = KeyEvent::new(KeyCode::Char('n'), KeyModifiers::NONE);
+        assert_eq!(map.lookup(&key), Some(Action::Next));
+        let key = KeyEvent::new(KeyCode::Char('9'), KeyModifiers::ALT);
+        assert_eq!(map.lookup(&key), Some(Action::Queue9));
+    }
+
+    #[test]
+    fn override_replaces_the_defaults() {
+        let map = build_keymap(&overrides(&[("next", &["ctrl+n"])])).unwrap();
+        let plain = KeyEvent::new(KeyCode::Char('n'), KeyModifiers::NONE);
+        let ctrl = KeyEvent::new(KeyCode::Char('n'), KeyModifiers::CONTROL);
+        assert_eq!(map.lookup(&plain), None);
+        assert_eq!(map.lookup(&ctrl), Some(Action::Next));
+    }
+
+    #[test]
+    fn reports_conflicts_and_reserved_digits() {
+        let problems = build_keymap(&overrides(&[("like", &["n"])])).err().unwrap();
+        assert!(problems.iter().any(|p| p.contains("'n' is bound to both")));
+
+        let problems = build_keymap(&overrides(&[("like", &["5"])])).err().unwrap();
+        assert!(problems.iter().any(|p| p.contains("reserved")));
+    }
+
+    #[test]
+    fn template_lists_exactly_the_defaults() {
+        // the commented out bindings of the generated keymap.toml
+        let listed: String = DEFAULT_KEYMAP
+            .lines()
+            .filter_map(|l| l.strip_prefix("# "))
+            .filter(|l| l.contains(" = [") && l.ends_with(']'))
+            .map(|l| format!("{}\n", l))
+            .collect();
+        let listed = parse_overrides(&listed).unwrap();
+        let defaults: HashMap<String, Vec<String>> = ACTIONS
+            .iter()
+            .map(|(n, _, c)| (n.to_string(), c.iter().map(|s| s.to_string()).collect()))
+            .collect();
+        assert_eq!(listed, defaults);
+        assert_eq!(
+            build_keymap(&listed).unwrap().bindings,
+            build_keymap(&HashMap::new()).unwrap().bindings
+        );
+    }
+
+    #[test]
+    fn unknown_command_in_file_is_an_error() {
+        assert!(parse_overrides("nope = [\"x\"]").is_err());
+        assert!(parse_overrides("next = 5").is_err());
+        let parsed = parse_overrides("next = \"x\"").unwrap();
+        assert_eq!(parsed["next"], vec!["x".to_string()]);
+    }
+}
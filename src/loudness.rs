@@ -0,0 +1,173 @@
+use crate::settings::{ReplayGainMode, StreamNormalize};
+use crate::{config, player};
+use lofty::prelude::*;
+use lofty::tag::Tag;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+// -------------------------------------------------------------------
+// keeps youtube opus and tidal flac at the same loudness. downloads get
+// an ebu r128 pass and replaygain tags (mpv reads them for local files),
+// streams have no tags so they go through loudnorm/dynaudnorm instead
+// -------------------------------------------------------------------
+
+// replaygain 2.0 reference level
+const REFERENCE_LUFS: f64 = -18.0;
+
+const FILTER_LABEL: &str = "norm";
+
+pub struct Analysis {
+    pub integrated_lufs: f64,
+    pub true_peak_dbfs: f64,
+}
+
+impl Analysis {
+    pub fn gain_db(&self) -> f64 {
+        REFERENCE_LUFS - self.integrated_lufs
+    }
+
+    pub fn peak(&self) -> f64 {
+        10f64.powf(self.true_peak_dbfs / 20.0)
+    }
+}
+
+// runs ffmpeg's ebur128 filter over the whole file, None if ffmpeg failed
+pub fn analyze(path: &Path) -> Option<Analysis> {
+    let out = Command::new("ffmpeg")
+        .args(["-hide_banner", "-nostats"])
+        .args(&config().network.ffmpeg_args)
+        .arg("-i")
+        .arg(path)
+        .args(["-af", "ebur128=peak=true", "-f", "null", "-"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .output()
+        .ok()?;
+    if !out.status.success() {
+        return None;
+    }
+    parse_summary(&String::from_utf8_lossy(&out.stderr))
+}
+
+// the summary comes last, so take the last "I:" / "Peak:" lines
+fn parse_summary(log: &str) -> Option<Analysis> {
+    let value = |key: &str| -> Option<f64> {
+        log.lines()
+            .rev()
+            .map(str::trim)
+            .find_map(|l| l.strip_prefix(key))
+            .and_then(|rest| rest.split_whitespace().next())
+            .and_then(|n| n.parse::<f64>().ok())
+    };
+    let integrated_lufs = value("I:")?;
+    // -70 is ebur128's "silence", a gain from that would be absurd
+    if integrated_lufs <= -70.0 {
+        return None;
+    }
+    Some(Analysis {
+        integrated_lufs,
+        true_peak_dbfs: value("Peak:").unwrap_or(0.0),
+    })
+}
+
+// track tags only, a single download knows nothing about the rest of its album
+// (so there is no album mode either)
+pub fn write_tags(tag: &mut Tag, analysis: &Analysis) {
+    tag.insert_text(
+        ItemKey::ReplayGainTrackGain,
+        format!("{:.2} dB", analysis.gain_db()),
+    );
+    tag.insert_text(
+        ItemKey::ReplayGainTrackPeak,
+        format!("{:.6}", analysis.peak()),
+    );
+}
+
+// mpv's --replaygain value
+pub fn replaygain_option() -> &'static str {
+    match config().loudness.replaygain {
+        ReplayGainMode::Track => "track",
+        ReplayGainMode::Off => "no",
+    }
+}
+
+fn stream_filter(mode: StreamNormalize) -> Option<&'static str> {
+    match mode {
+        StreamNormalize::Loudnorm => Some("lavfi=[loudnorm=I=-16:TP=-1.5:LRA=11]"),
+        StreamNormalize::Dynaudnorm => Some("lavfi=[dynaudnorm=f=500:g=31]"),
+        StreamNormalize::Off => None,
+    }
+}
+
+// streams get the normaliser, local files their tags
+fn filter_for(source: &str, mode: StreamNormalize) -> Option<&'static str> {
+    let remote = source.starts_with("http") || source.ends_with(".mpd");
+    stream_filter(mode).filter(|_| remote)
+}
+
+// called before every track
+pub fn prepare(source: &str) {
+    player::set_filter(FILTER_LABEL, filter_for(source, config().loudness.stream));
+}
+
+// mpv switches to a preloaded track with the filters it already has, so
+// only a track with the same normalisation can follow gaplessly
+pub fn can_follow(current: &str, next: &str) -> bool {
+    let mode = config().loudness.stream;
+    filter_for(current, mode) == filter_for(next, mode)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the tail of `ffmpeg -af ebur128=peak=true -f null -`
+    const SUMMARY: &str = "\
+[Parsed_ebur128_0 @ 0x5581] t: 199.9 TARGET:-23 LUFS M: -9.1 S: -8.7 I: -8.9 LUFS LRA: 5.1 LU FTPK: -0.3 dBFS TPK: 0.4 dBFS
+[Parsed_ebur128_0 @ 0x5581] Summary:
+
+  Integrated loudness:
+    I:          -9.4 LUFS
+    Threshold: -19.6 LUFS
+
+  Loudness range:
+    LRA:         5.3 LU
+    Threshold: -29.7 LUFS
+    LRA low:   -13.7 LUFS
+    LRA high:   -8.4 LUFS
+
+  True peak:
+    Peak:        0.6 dBFS
+";
+
+    #[test]
+    fn reads_the_summary_not_the_progress_lines() {
+        let a = parse_summary(SUMMARY).unwrap();
+        assert_eq!(a.integrated_lufs, -9.4);
+        assert_eq!(a.true_peak_dbfs, 0.6);
+        assert!((a.gain_db() - -8.6).abs() < 1e-9);
+        assert!((a.peak() - 1.0715).abs() < 1e-3);
+    }
+
+    #[test]
+    fn silence_or_garbage_gives_nothing() {
+        assert!(parse_summary("").is_none());
+        assert!(parse_summary("ffmpeg: no such file").is_none());
+        assert!(parse_summary("  I:         -70.0 LUFS\n").is_none());
+        // no peak line: assume full scale
+        let a = parse_summary("  I:         -18.0 LUFS\n").unwrap();
+        assert_eq!(a.gain_db(), 0.0);
+        assert_eq!(a.true_peak_dbfs, 0.0);
+    }
+
+    #[test]
+    fn only_streams_get_the_normaliser() {
+        let stream = "https://rr1.googlevideo.com/videoplayback?id=1";
+        for mode in [StreamNormalize::Loudnorm, StreamNormalize::Dynaudnorm] {
+            assert!(filter_for(stream, mode).is_some());
+            assert!(filter_for("/tmp/manifest.mpd", mode).is_some());
+            assert_eq!(filter_for("/music/a.opus", mode), None);
+        }
+        assert_eq!(filter_for(stream, StreamNormalize::Off), None);
+    }
+}
@@ -1,8 +1,30 @@
 mod api;
+mod backend;
+mod cli;
+mod control;
+mod effects;
+mod failover;
 mod features;
 mod flac;
+mod history;
+mod instance;
+mod keymap;
+mod loudness;
+#[cfg(target_os = "linux")]
+mod mpris;
+mod mpv_ipc;
+mod net;
 mod offline;
+mod output;
 mod player;
+mod quality;
+mod resolve;
+mod scrobble;
+mod session;
+mod settings;
+mod stats;
+mod tempo;
+mod timer;
 mod ui1;
 mod ui2;
 mod ui3;
@@ -10,9 +32,10 @@ mod ui_common;
 
 use crate::api::SongDetails;
 use crate::player::clear_temp;
+use crate::settings::{AppConfig, RestoreMode};
 use crate::ui_common::set_status_line;
 use crate::{
-    flac::fetch_flac_stream_url,
+    backend::PlayerEvent,
     flac::init_api,
     offline::get_excluded_titles,
     ui1::{show_playlists, show_songs},
@@ -26,7 +49,6 @@ use crossterm::{
 };
 use std::collections::VecDeque;
 use std::io::stdout;
-use std::process::Child;
 use std::sync::atomic::{AtomicBool, AtomicI64, AtomicUsize, Ordering};
 use std::sync::{OnceLock, RwLock, mpsc};
 use std::thread;
@@ -36,17 +58,7 @@ use tokio::time;
 // DATA STRUCTURES
 // -------------------------------------------------------------------
 
-#[derive(Debug)]
-pub struct AppConfig {
-    pub offline_mode: bool,
-    pub no_autoplay: bool,
-    pub lossless_mode: bool,
-    pub peak_lossless_mode: bool,
-    pub game_mode: bool,
-    pub download_mode: bool,
-}
-
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct Track {
     pub title: String,
     pub duration: String,
@@ -89,6 +101,20 @@ impl Track {
             "".to_string(),                // url
         )
     }
+
+    // "Song" by "Artist", 3:00, the fixture every test module starts from
+    #[cfg(test)]
+    pub fn sample(video_id: Option<&str>, url: &str) -> Self {
+        Self::new(
+            "Song".to_string(),
+            vec!["Artist".to_string()],
+            String::new(),
+            "3:00".to_string(),
+            None,
+            video_id.map(String::from),
+            url.to_string(),
+        )
+    }
 }
 
 // ----------------------------------------------------------------------------------
@@ -100,7 +126,6 @@ static SONG_QUEUE: RwLock<Vec<Track>> = RwLock::new(Vec::new());
 // STORES ALL DETAILS OF UPCOMING SONGS
 static RELATED_SONG_LIST: RwLock<Vec<api::SongDetails>> = RwLock::new(Vec::new());
 static RECENTLY_PLAYED: RwLock<VecDeque<Track>> = RwLock::new(VecDeque::new());
-const HISTORY_LIMIT: usize = 50;
 //TO KEEP CONSISTENT VOLUME LEVEL ACROSS TRACKS (TO BE READ BY player.rs)
 pub static VOLUME: AtomicI64 = AtomicI64::new(75);
 
@@ -123,29 +148,96 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // PART 1 - GET ARGUMENTS, INITIAL GLOBAL (STATIC) VARIABLES
     // ----------------------------------------------------------------------------------
     let args: Vec<String> = std::env::args().collect();
-    let app_config = AppConfig {
-        download_mode: args.iter().any(|a| a == "--download" || a == "-d"), //save songs to music_dir
-        offline_mode: args.iter().any(|a| a == "--offline" || a == "-o"), //play only from offline library
-        no_autoplay: args.iter().any(|a| a == "--nomix" || a == "-n"),    //dont auto queue
-        lossless_mode: args
-            .iter()
-            .any(|a| a == "--lossless" || a == "-l" || a == "--peak-lossless" || a == "-pl"), //try fetching from tidal
-        peak_lossless_mode: args.iter().any(|a| a == "--peak-lossless" || a == "-pl"), //try fetching peak from tidal
-        game_mode: args.iter().any(|a| a == "--guess" || a == "-g"), //guess quality (to be used with --lossless)
+    let cli = match cli::parse_args(&args) {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("whytui: {}\nTry 'whytui --help' for more information.", e);
+            std::process::exit(2);
+        }
     };
+    match cli.command {
+        cli::Command::Help => {
+            print!("{}", cli::HELP);
+            return Ok(());
+        }
+        cli::Command::Version => {
+            println!("whytui {}", env!("CARGO_PKG_VERSION"));
+            return Ok(());
+        }
+        _ => {}
+    }
+    // config.toml first, then let the command line flags override it
+    let mut app_config: AppConfig = match settings::load_config() {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    app_config.apply_cli_flags(&cli.flags);
+    // --profile can only be checked once the file is read
+    if let Some(p) = &cli.flags.profile
+        && !app_config.stream.profiles.contains_key(p)
+    {
+        eprintln!("--profile: no [stream.profiles.{}] in config.toml", p);
+        std::process::exit(1);
+    }
     // Set the global OnceLock
     CONFIG.set(app_config).expect("Failed to set config");
 
+    // a tui may already be running, see [remote] already_running
+    let is_tui = cli.command == cli::Command::Tui;
+    if (is_tui || matches!(cli.command, cli::Command::Play { .. }))
+        && instance::handle_already_running(&args, is_tui)
+    {
+        return Ok(());
+    }
+
+    VOLUME.store(config().playback.volume, Ordering::Relaxed);
+    UI_MODE.store(config().ui.mode - 1, Ordering::Relaxed);
+    ui_common::LYRIC_DISPLAY_MODE.store(config().lyrics.display_mode.as_u8(), Ordering::Relaxed);
+
     //set default view mode to queue
     *VIEW_MODE.write().unwrap() = "queue".to_string();
     //create music_dir and temp dir to store currently playing song
     let music_dir = player::prepare_music_dir()?;
-    //set cookie path
-    let cookies_path = music_dir.join("config/cookies.txt");
-    //Custom unofficial apiz ( call with cookies if available)
-    let yt_client = api::YTMusic::new_with_cookies(cookies_path.to_str().unwrap()).unwrap();
+    //Custom unofficial apiz ( call with cookies if available, see [network] cookies)
+    let yt_client = api::YTMusic::new_with_cookies(&net::read_cookies())?;
+    resolve::init(yt_client.clone());
+    // pick up a newer innertube client version in the background (cached for next launch)
+    if cli.command.uses_youtube() {
+        let yt = yt_client.clone();
+        tokio::spawn(async move {
+            let _ = yt.refresh_client_version().await;
+        });
+    }
+
+    // eq preset, pitch and output device from last time, applied once mpv starts
+    effects::init();
+    tempo::init();
+    output::init();
+    quality::init();
+
+    // subcommands (search, play, download, lyrics...) never touch the tui
+    if cli.command != cli::Command::Tui {
+        let result = cli::run(cli.command, &yt_client, &music_dir).await;
+        instance::release();
+        if let Err(e) = result {
+            eprintln!("whytui: {}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    if let Err(e) = keymap::load_keymap() {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+    // send whatever failed to scrobble last time
+    scrobble::flush_queue();
+
     //mpv handle to extract child and stop songs if needed
-    let mut currently_playing: Option<Child> = None;
+    let mut currently_playing: Option<player::Playback> = None;
     //contains song details (including vid_id for online songs)
     let mut current_track: Option<Track> = None;
     // CLEAR SCREEN BEFORE STARTING THE REAL SHIT
@@ -180,6 +272,20 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     //      }
     // });
     let (tx, rx) = mpsc::channel::<String>();
+    // remote controls and timers send the same commands as the keyboard but on
+    // their own channel: menus read rx, and a `ctl next` must not page a menu
+    let (remote_tx, remote_rx) = mpsc::channel::<String>();
+    #[cfg(target_os = "linux")]
+    mpris::spawn(remote_tx.clone());
+    control::spawn(remote_tx.clone());
+    timer::spawn(remote_tx.clone());
+    // --sleep / --alarm go through the same commands as typing them
+    if let Some(spec) = &cli.flags.sleep {
+        let _ = remote_tx.send(format!("sleep={}", spec));
+    }
+    if let Some(spec) = &cli.flags.alarm {
+        let _ = remote_tx.send(format!("alarm={}", spec));
+    }
     spawn_input_handler(tx);
     //
     //
@@ -213,18 +319,64 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         std::thread::sleep(std::time::Duration::from_millis(500));
     }
     // ----------------------------------------------------------------------------------
+    // CASE 0 : OFFER TO RESUME THE LAST SESSION (TRACK, POSITION, QUEUE, CONTEXT)
+    // ----------------------------------------------------------------------------------
+    let mut resumed = false;
+    if config().session.restore != RestoreMode::Never
+        && let Some(session) = session::load()
+        && let Some(track) = session.current.clone()
+        && (!config().offline_mode || !resolve::is_remote(&track))
+    {
+        let wants_resume = config().session.restore == RestoreMode::Always
+            || ask_resume(&rx, &track, session.position);
+
+        let _ = execute!(stdout(), Clear(ClearType::All));
+
+        if wants_resume {
+            session::restore_state(&session);
+            set_status_line(Some("Resuming...".to_string()));
+
+            // the queue only holds what to play, streams are looked up when they come up
+            match player::play_track(&track, &music_dir, Some(session.position)).await {
+                Ok(p) => {
+                    current_track = Some(track.clone());
+                    currently_playing = Some(p);
+                    resumed = true;
+
+                    if let Some(v) = track.video_id.clone()
+                        && !config().no_autoplay
+                        && !config().offline_mode
+                    {
+                        let yt = yt_client.clone();
+                        tokio::spawn(async move {
+                            queue_auto_add_online(yt, v).await;
+                        });
+                    }
+
+                    refresh_ui(Some(&track));
+                    set_status_line(Some("RESUMED SESSION".to_string()));
+                }
+                Err(e) => {
+                    set_status_line(Some(format!(":( {}", e)));
+                }
+            }
+        }
+    }
+
+    // a resumed session is already playing, nothing else to start
+    // ----------------------------------------------------------------------------------
     // CASE 1 : IF OFFLINE MODE INITIAL FETCH RANDOM SONG + POPULATE QUEUE
     // ----------------------------------------------------------------------------------
-    if config().offline_mode {
+    if !resumed && config().offline_mode {
         let exclude = get_excluded_titles();
         {
             let mut q = SONG_QUEUE.write().unwrap();
             offline::populate_queue_offline(&music_dir, &mut q, &exclude);
         }
 
-        if let Some(track) = queue_next() {
+        if let Some((track, p)) = play_next_queued(None, &music_dir).await {
             current_track = Some(track.clone()); //to pass it around to functions like next song
-            currently_playing = Some(player::play_file(&track.url, &track, &music_dir)?); //object to stop the current song
+            currently_playing = Some(p); //object to stop the current song
             refresh_ui(Some(&track));
         } else {
             set_status_line(Some(format!("No local songs found!")));
@@ -235,7 +387,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // ----------------------------------------------------------------------------------
     // CASE 2 : IF ONLINE MODE TRY TO CONNECT TO API AND FETCH USERNAME
     // ----------------------------------------------------------------------------------
-    else {
+    else if !resumed {
         let user_status = yt_client
             .fetch_account_name()
             .await
@@ -251,10 +403,19 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     //
     //
 
+    let mut last_session_save = std::time::Instant::now();
+    let session_interval = Duration::from_secs(config().session.save_interval_secs);
+
     // -------------------------------------------------------------------
     // START OF GAME LOOP (BOTH ONLINE,OFFLINE)
     // -------------------------------------------------------------------
     loop {
+        // keep session.json fresh so a crash/kill can still be resumed
+        if currently_playing.is_some() && last_session_save.elapsed() >= session_interval {
+            session::save(current_track.as_ref());
+            last_session_save = std::time::Instant::now();
+        }
+
         // if event::poll(Duration::from_millis(0))? {
         //      match event::read()? {
         //          Event::Resize(_, _) => {
@@ -272,18 +433,55 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                             // Reaping previous song instance thus mutable reference needed ( also to check if finished naturally)
                             // if finished fully move song from /temp folder to music_dir
                             //
-                            if let Ok(Some(_)) = child.try_wait() {
+                            // Ended = player went quiet, Advanced = it already moved on to the preloaded track
+                            if let Some(event) = child.poll() {
+                                // mpv gave up on the track or it stopped long before its end:
+                                // carry on from a fresh url or the next source, skip it if none plays
+                                let mut failed = false;
+                                if let PlayerEvent::Ended { error, position } = &event
+                                    && let Some(track) = &current_track
+                                    && let Some(failure) =
+                                        failover::classify(track, error.as_deref(), *position)
+                                {
+                                    let reason = failover::describe(failure, error.as_deref());
+                                    let failed_source = child.source().to_string();
+                                    if let Some((p, note)) = recover_playback(
+                                        track,
+                                        failure,
+                                        &reason,
+                                        failed_source,
+                                        position.map(|(pos, _)| pos),
+                                        &music_dir,
+                                    )
+                                    .await
+                                    {
+                                        currently_playing = Some(p);
+                                        set_status_line(Some(note));
+                                        continue;
+                                    }
+                                    failover::mark(track, &reason);
+                                    set_status_line(Some(format!(
+                                        ":( Skipped {}: {}",
+                                        track.title, reason
+                                    )));
+                                    failed = true;
+                                } else if let Some(track) = &current_track {
+                                    failover::clear(track);
+                                }
+
                                 ui_common::clear_lyrics();
 
                                 if let Some(track) = &current_track {
-                                    add_to_history(track.clone());
+                                    add_to_history(track.clone(), failed);
 
                                     if config().download_mode
-                                        && (track.url.starts_with("http") || track.url.ends_with(".mpd"))
+                                        && !failed
+                                        && let Some(url) = resolve::last_source(track)
+                                        && (url.starts_with("http") || url.ends_with(".mpd"))
                                     {
                                         let track_clone = track.clone();
                                         let music_dir_clone = music_dir.clone();
-                                        let url_clone = track.url.clone();
+                                        let url_clone = url;
 
                                         std::thread::spawn(move || {
                                             if let Err(e) = player::background_download(
@@ -291,7 +489,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                                                 &track_clone,
                                                 &music_dir_clone,
                                             ) {
-                                                eprintln!("Download failed: {}", e);
+                                                set_status_line(Some(format!(
+                                                    ":( Download failed: {}",
+                                                    e
+                                                )));
                                             }
                                         });
                                     }
@@ -299,16 +500,28 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
                                 currently_playing = None;
 
+                                // sleep timer counting songs ran out with this one
+                                let sleep_over = timer::track_ended();
+                                if sleep_over {
+                                    current_track = None;
+                                    player::stop();
+                                    refresh_ui(Some(&Track::dummy()));
+                                    set_status_line(Some("SLEEP TIMER: STOPPED".to_string()));
+                                }
+
                                 // -------------------------------------------------------------------
                                 // CASE 2 : WHEN NO SONG IS PLAYING CURRENTLY
                                 // -------------------------------------------------------------------
                                 //
                                 let should_repeat = REPEAT_MODE.load(Ordering::Relaxed);
 
-                                if let Some(track) = &current_track {
+                                if let Some(track) = &current_track
+                                    && !failed
+                                {
                                     if should_repeat > 0 {
-                                        currently_playing =
-                                            Some(player::play_file(&track.url, track, &music_dir)?);
+                                        currently_playing = Some(
+                                            player::continue_with(&event, track, &music_dir).await?,
+                                        );
                                         if should_repeat == 1 {
                                         REPEAT_MODE.store(0, Ordering::Relaxed);
                                         }
@@ -316,11 +529,12 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                                 }
 
                                 //if not repeating play from queue
-                                if currently_playing.is_none() {
-                                    if let Some(track) = queue_next() {
+                                if currently_playing.is_none() && !sleep_over {
+                                    if let Some((track, p)) =
+                                        play_next_queued(Some(&event), &music_dir).await
+                                    {
                                         current_track = Some(track.clone());
-                                        currently_playing =
-                                            Some(player::play_file(&track.url, &track, &music_dir)?);
+                                        currently_playing = Some(p);
 
                                         // -------------------------------------------------------------------
                                         // CASE 2.1 : IF AUTOPLAY IS ENABLED (DEFAULT MODE)
@@ -356,13 +570,26 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                                     // -------------------------------------------------------------------
                                     else {
                                         current_track = None;
-                                        crate::IS_PLAYING.store(false, Ordering::SeqCst);
+                                        // a preloaded track may already be playing
+                                        player::stop();
                                         refresh_ui(Some(&Track::dummy()));
                                     }
                                 }
                             }
                         }
 
+        // keep the backend's next track in line with the queue so the switch is gapless
+        if let Some(playing) = &currently_playing {
+            let next = if timer::stops_after_current() {
+                None
+            } else if REPEAT_MODE.load(Ordering::Relaxed) > 0 {
+                current_track.clone()
+            } else {
+                SONG_QUEUE.read().unwrap().first().cloned()
+            };
+            player::preload(playing.source(), next.as_ref());
+        }
+
         //
         //
         //
@@ -372,6 +599,19 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         // -------------------------------------------------------------------
         // PART 5 - CHECK FOR ANY INPUT FROM USER VIA RX
         // -------------------------------------------------------------------
+        // remote commands first, they are never searched for
+        if let Ok(cmd) = remote_rx.try_recv() {
+            handle_global_commands(
+                &cmd,
+                &rx,
+                &yt_client,
+                &mut current_track,
+                &mut currently_playing,
+                &music_dir,
+            )
+            .await;
+            continue;
+        }
         let input = match rx.try_recv() {
             Ok(s) => s,
             Err(_) => {
@@ -421,7 +661,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         //         USE RECIEVED TEXT TO SEARCH CUSTOM API
         // -------------------------------------------------------------------
         let mut songs: Vec<api::SongDetails> = Vec::new();
-        songs = match yt_client.search_songs(&input, 5).await {
+        songs = match yt_client.search_songs(&input, config().ui.search_results).await {
             Ok(s) => s,
             Err(_) => {
                 std::thread::sleep(Duration::from_millis(75));
@@ -469,7 +709,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 None,
             )
             .await
-            .unwrap_or_else(|e| println!("Auto-select error: {}", e));
+            .unwrap_or_else(|e| set_status_line(Some(format!(":( {}", e))));
             //finish this loop
             continue;
         }
@@ -501,7 +741,7 @@ async fn handle_global_commands(
     rx: &std::sync::mpsc::Receiver<String>,
     yt_client: &api::YTMusic,
     current_track: &mut Option<Track>,
-    currently_playing: &mut Option<Child>,
+    currently_playing: &mut Option<player::Playback>,
     music_dir: &std::path::PathBuf,
 ) -> bool {
     // let title_ref = current_track.as_ref().map(|t| t.title.as_str()); //now playing song title to pass to refresh_ui
@@ -525,11 +765,16 @@ async fn handle_global_commands(
             return true;
         }
         "q" | "quit" => {
+            session::save(current_track.as_ref());
             if let Some(track) = current_track {
-                add_to_history(track.clone());
+                add_to_history(track.clone(), false);
                 player::stop_process(currently_playing, &track.title, music_dir);
             }
             refresh_ui(None);
+            player::shutdown();
+            control::cleanup();
+            instance::release();
+            restore_terminal();
             std::process::exit(0);
         }
         // "s" | "stop" => {
@@ -542,35 +787,242 @@ async fn handle_global_commands(
         //     set_status_line(Some(format!("STOPPED SONG")));
         //     return true;
         // }
+        // next eq / effect preset, "eq=<name>" picks one (whytui ctl eq)
+        "e" | "effects" => {
+            let name = effects::cycle();
+            set_status_line(Some(format!("EFFECTS {}", name.to_uppercase())));
+            return true;
+        }
+        s if s.starts_with("eq=") => {
+            match effects::set(&s[3..]) {
+                Ok(()) => set_status_line(Some(format!("EFFECTS {}", s[3..].to_uppercase()))),
+                Err(e) => set_status_line(Some(format!(":( {}", e))),
+            }
+            return true;
+        }
+        // playback speed for this kind of track, "speed=<x>" sets it (whytui ctl speed)
+        "speed+" | "speed-" => {
+            let step = if input == "speed+" {
+                tempo::SPEED_STEP
+            } else {
+                -tempo::SPEED_STEP
+            };
+            let speed = tempo::set_speed(tempo::speed() + step);
+            set_status_line(Some(format!("SPEED {:.2}x", speed)));
+            return true;
+        }
+        s if s.starts_with("speed=") => {
+            match s[6..].parse::<f64>() {
+                Ok(v) => {
+                    let speed = tempo::set_speed(v);
+                    set_status_line(Some(format!("SPEED {:.2}x", speed)));
+                }
+                Err(_) => set_status_line(Some(format!(":( bad speed '{}'", &s[6..]))),
+            }
+            return true;
+        }
+        // pitch shift in semitones, independent of speed
+        "pitch+" | "pitch-" => {
+            let step = if input == "pitch+" { 1 } else { -1 };
+            let pitch = tempo::set_pitch(tempo::pitch() + step);
+            set_status_line(Some(format!("PITCH {:+} SEMITONES", pitch)));
+            return true;
+        }
+        s if s.starts_with("pitch=") => {
+            match s[6..].parse::<i64>() {
+                Ok(v) => {
+                    let pitch = tempo::set_pitch(v);
+                    set_status_line(Some(format!("PITCH {:+} SEMITONES", pitch)));
+                }
+                Err(_) => set_status_line(Some(format!(":( bad pitch '{}'", &s[6..]))),
+            }
+            return true;
+        }
+        // sleep timer: `z` steps through the presets, "sleep=<spec>" sets one
+        "z" => {
+            let spec = timer::cycle_sleep();
+            set_status_line(Some(timer::describe_sleep(spec)));
+            return true;
+        }
+        s if s.starts_with("sleep=") => {
+            match timer::parse_sleep(&s[6..]) {
+                Ok(spec) => {
+                    timer::set_sleep(spec);
+                    set_status_line(Some(timer::describe_sleep(spec)));
+                }
+                Err(e) => set_status_line(Some(format!(":( {}", e))),
+            }
+            return true;
+        }
+        s if s.starts_with("alarm=") => {
+            let alarm = match timer::parse_alarm(&s[6..]) {
+                Ok(alarm) => alarm,
+                Err(e) => {
+                    set_status_line(Some(format!(":( {}", e)));
+                    return true;
+                }
+            };
+            if config().offline_mode
+                && matches!(&alarm, Some((_, timer::AlarmTarget::Playlist(_))))
+            {
+                set_status_line(Some(":( Playlists need online mode".to_string()));
+                return true;
+            }
+            timer::set_alarm(alarm);
+            match timer::alarm_text() {
+                Some(text) => set_status_line(Some(text)),
+                None => set_status_line(Some("ALARM OFF".to_string())),
+            }
+            return true;
+        }
+        "timer:sleep" => {
+            if let Some(track) = current_track.take() {
+                add_to_history(track.clone(), false);
+                player::stop_process(currently_playing, &track.title, music_dir);
+            }
+            // the fade is over, the next song plays at the normal volume again
+            player::set_volume(VOLUME.load(Ordering::Relaxed));
+            refresh_ui(Some(&Track::dummy()));
+            set_status_line(Some("SLEEP TIMER: STOPPED".to_string()));
+            return true;
+        }
+        "timer:alarm" => {
+            let Some(target) = timer::take_fired_alarm() else {
+                return true;
+            };
+            if let Some(track) = current_track.take() {
+                add_to_history(track.clone(), true);
+                player::stop_process(currently_playing, &track.title, music_dir);
+            }
+            match target {
+                timer::AlarmTarget::Offline => {
+                    let exclude = get_excluded_titles();
+                    {
+                        let mut q = SONG_QUEUE.write().unwrap();
+                        q.clear();
+                        offline::populate_queue_offline(music_dir, &mut q, &exclude);
+                    }
+                    match play_next_queued(None, music_dir).await {
+                        Some((track, p)) => {
+                            *current_track = Some(track.clone());
+                            *currently_playing = Some(p);
+                            refresh_ui(Some(&track));
+                            set_status_line(Some("GOOD MORNING".to_string()));
+                        }
+                        None => set_status_line(Some("No local songs found!".to_string())),
+                    }
+                }
+                timer::AlarmTarget::Playlist(id) => {
+                    match yt_client.fetch_playlist_songs(&id, 100).await {
+                        Ok((songs, _)) if !songs.is_empty() => {
+                            let _ = handle_song_selection(
+                                "1".to_string(),
+                                &songs,
+                                music_dir,
+                                yt_client,
+                                current_track,
+                                currently_playing,
+                                Some((id, false)),
+                            )
+                            .await;
+                            set_status_line(Some("GOOD MORNING".to_string()));
+                        }
+                        _ => set_status_line(Some(":( Alarm couldn't load the playlist".to_string())),
+                    }
+                }
+            }
+            return true;
+        }
         "c" | "clear" => {
             SONG_QUEUE.write().unwrap().clear();
             set_status_line(Some(format!("QUEUE CLEARED")));
             return true;
         }
         s if s == "+" || s == "-" => {
-            let mut delta: i64 = if s == "+" { 5 } else { -5 };
+            let step = config().playback.volume_step;
+            let mut delta: i64 = if s == "+" { step } else { -step };
 
             while let Ok(next_input) = rx.try_recv() {
                 if next_input == "+" {
-                    delta += 5;
+                    delta += step;
                 } else if next_input == "-" {
-                    delta -= 5;
+                    delta -= step;
                 } else {
                     break;
                 }
             }
 
             let current = VOLUME.load(Ordering::Relaxed);
-            let new_vol = (current + delta).clamp(0, 150);
+            let new_vol = (current + delta).clamp(0, config().playback.max_volume);
             VOLUME.store(new_vol, Ordering::Relaxed);
 
-            player::vol_change(delta);
+            player::set_volume(new_vol);
 
             set_status_line(Some(format!("VOLUME {}", new_vol)));
 
             return true;
         }
 
+        // volume from remote controls, "vol=60" absolute, "vol=+5" / "vol=-5" relative
+        s if s.starts_with("vol=") => {
+            let value = &s[4..];
+            if let Ok(n) = value.parse::<i64>() {
+                let target = if value.starts_with(['+', '-']) {
+                    VOLUME.load(Ordering::Relaxed) + n
+                } else {
+                    n
+                };
+                let new_vol = target.clamp(0, config().playback.max_volume);
+                VOLUME.store(new_vol, Ordering::Relaxed);
+                player::set_volume(new_vol);
+                set_status_line(Some(format!("VOLUME {}", new_vol)));
+            }
+            return true;
+        }
+
+        // queue the top search hit / a videoId, sent by `whytui ctl enqueue`.
+        // play_id= plays it right away (a forwarded `whytui play <id>`)
+        s if s.starts_with("enqueue=")
+            || s.starts_with("enqueue_id=")
+            || s.starts_with("play_id=") =>
+        {
+            if config().offline_mode {
+                set_status_line(Some("Nope not here".to_string()));
+                return true;
+            }
+            let play_now = s.starts_with("play_id=");
+            let songs = match s.strip_prefix("enqueue=") {
+                Some(query) => yt_client.search_songs(query, 1).await.unwrap_or_default(),
+                None => yt_client
+                    .fetch_song_details(s.split_once('=').map_or("", |(_, id)| id))
+                    .await
+                    .map(|song| vec![song])
+                    .unwrap_or_default(),
+            };
+            let Some(first) = songs.first() else {
+                set_status_line(Some(":( Couldn't find that song".to_string()));
+                return true;
+            };
+            let title = first.title.clone();
+            let selection = if play_now { "1" } else { "q1" };
+            match handle_song_selection(
+                selection.to_string(),
+                &songs,
+                music_dir,
+                yt_client,
+                current_track,
+                currently_playing,
+                None,
+            )
+            .await
+            {
+                Ok(()) if play_now => {}
+                Ok(()) => set_status_line(Some(format!("Queued {}", title))),
+                Err(_) => set_status_line(Some(":( Error queueing song".to_string())),
+            }
+            return true;
+        }
+
         "pause" => {
             if currently_playing.is_some() {
                 player::toggle_pause();
@@ -673,14 +1125,72 @@ async fn handle_global_commands(
             return true;
         }
         "r" | "recents" => {
-            {
+            // this layout has no room for the browser, it keeps the queue / recents toggle
+            if UI_MODE.load(Ordering::Relaxed) == 2 {
                 let mut mode = VIEW_MODE.write().unwrap();
                 *mode = if *mode == "queue" {
                     "recent".to_string()
                 } else {
                     "queue".to_string()
                 };
+                drop(mode);
+                refresh_ui(None);
+                return true;
+            }
+
+            *VIEW_MODE.write().unwrap() = "recent".to_string();
+            if let Err(e) =
+                handle_history_browsing(rx, yt_client, music_dir, current_track, currently_playing)
+                    .await
+            {
+                set_status_line(Some(format!("Error in History: {}", e)));
             }
+            *VIEW_MODE.write().unwrap() = "queue".to_string();
+
+            refresh_ui(None);
+            return true;
+        }
+        // output device picker, "device=<name>" switches directly (whytui ctl device)
+        "o" | "device" => {
+            if UI_MODE.load(Ordering::Relaxed) == 2 {
+                refresh_ui(None);
+                return true;
+            }
+            handle_device_picker(rx);
+            refresh_ui(None);
+            return true;
+        }
+        s if s.starts_with("device=") => {
+            match output::set(&s[7..]) {
+                Ok(()) => set_status_line(Some(format!("AUDIO: {}", output::describe(&s[7..])))),
+                Err(e) => set_status_line(Some(format!(":( {}", e))),
+            }
+            return true;
+        }
+        // stream quality: data saver on/off, "profile=<name>" from [stream.profiles]
+        "datasaver" => {
+            let on = quality::toggle_data_saver();
+            resolve::forget_all();
+            set_status_line(Some(format!("DATA SAVER {}", if on { "ON" } else { "OFF" })));
+            return true;
+        }
+        s if s.starts_with("profile=") => {
+            if let Err(e) = quality::set_profile(s[8..].trim()) {
+                set_status_line(Some(format!(":( {}", e)));
+                return true;
+            }
+            resolve::forget_all();
+            let name = quality::profile();
+            let name = if name.is_empty() { "default" } else { name.as_str() };
+            set_status_line(Some(format!("STREAM PROFILE {}", name.to_uppercase())));
+            return true;
+        }
+        "S" | "stats" => {
+            if UI_MODE.load(Ordering::Relaxed) == 2 {
+                refresh_ui(None);
+                return true;
+            }
+            handle_stats_screen(rx);
             refresh_ui(None);
             return true;
         }
@@ -701,15 +1211,14 @@ async fn handle_global_commands(
                 }
         "n" | "next" => {
             if let Some(track) = current_track {
-                add_to_history(track.clone());
+                add_to_history(track.clone(), true);
                 player::stop_process(currently_playing, &track.title, music_dir);
             }
 
-            if let Some(track) = queue_next() {
+            if let Some((track, p)) = play_next_queued(None, music_dir).await {
                 ui_common::clear_lyrics();
                 *current_track = Some(track.clone());
-                *currently_playing =
-                    Some(player::play_file(&track.url, &track, music_dir).unwrap());
+                *currently_playing = Some(p);
 
                 if !config().no_autoplay {
                     if config().offline_mode {
@@ -739,15 +1248,24 @@ async fn handle_global_commands(
             if let Some(track) = current_track {
                 if let Some(prev_track) = get_prev_track() {
                     ui_common::clear_lyrics();
+                    history::finish(true);
 
                     player::stop_process(currently_playing, &track.title, music_dir);
                     queue_add_front(track.clone());
 
-                    *current_track = Some(prev_track.clone());
-                    *currently_playing =
-                        Some(player::play_file(&prev_track.url, &prev_track, music_dir).unwrap());
-                    refresh_ui(Some(&prev_track));
-                    set_status_line(Some(format!("PLAYING PREVIOUS")));
+                    match player::play_track(&prev_track, music_dir, None).await {
+                        Ok(p) => {
+                            *current_track = Some(prev_track.clone());
+                            *currently_playing = Some(p);
+                            refresh_ui(Some(&prev_track));
+                            set_status_line(Some(format!("PLAYING PREVIOUS")));
+                        }
+                        Err(e) => {
+                            *current_track = None;
+                            refresh_ui(Some(&Track::dummy()));
+                            set_status_line(Some(format!(":( {}", e)));
+                        }
+                    }
                 }
             }
             return true;
@@ -814,13 +1332,14 @@ async fn handle_global_commands(
             return true;
         }
         s if s == "[" || s == "]" => {
-            let mut delta: i64 = if s == "]" { 100 } else { -100 };
+            let step = config().lyrics.offset_step_ms;
+            let mut delta: i64 = if s == "]" { step } else { -step };
 
             while let Ok(next_input) = rx.try_recv() {
                 if next_input == "]" {
-                    delta += 100;
+                    delta += step;
                 } else if next_input == "[" {
-                    delta -= 100;
+                    delta -= step;
                 } else {
                     break;
                 }
@@ -844,7 +1363,7 @@ pub async fn handle_song_selection(
     music_dir: &PathBuf,
     yt_client: &api::YTMusic,
     current_track: &mut Option<Track>,
-    currently_playing: &mut Option<Child>,
+    currently_playing: &mut Option<player::Playback>,
     playlist_context: Option<(String, bool)>,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let input = selection_input.trim();
@@ -856,44 +1375,8 @@ pub async fn handle_song_selection(
 
     if idx >= 1 && idx <= songs_list.len() {
         let selected = &songs_list[idx - 1];
-        let safe_title = player::the_naming_format_in_which_i_have_saved_the_track_locally(
-            &selected.title,
-            &selected.artists,
-        );
-
-        let opus = music_dir.join(format!("{}.opus", safe_title));
-        let flac = music_dir.join(format!("{}.flac", safe_title));
-
-        let src = if flac.exists() {
-            flac.to_string_lossy().to_string()
-        } else if opus.exists() {
-            opus.to_string_lossy().to_string()
-        } else {
-            let clean_title =
-                if_title_contains_non_english_and_other_language_script_return_only_english_part(
-                    &selected.title,
-                );
-            let query = format!("{} {}", clean_title, selected.artists.join(","));
-            let mut final_url = None;
-
-            if config().lossless_mode {
-                if !config().game_mode {
-                    set_status_line(Some("Trying to fetch lossless".to_string()));
-                }
-                final_url = fetch_flac_stream_url(&query, &selected.duration).await.ok();
-            }
-            if final_url.is_none() {
-                if !config().game_mode {
-                    set_status_line(Some("Fetching from youtube".to_string()));
-                }
-                final_url = yt_client.fetch_stream_url(&selected.video_id).await.ok();
-            }
-            match final_url {
-                Some(url) => url,
-                None => return Ok(()),
-            }
-        };
 
+        // no url yet, a cached copy or a fresh stream is picked when it plays
         let new_track = Track::new(
             selected.title.clone(),
             selected.artists.clone(),
@@ -901,15 +1384,22 @@ pub async fn handle_song_selection(
             selected.duration.clone(),
             selected.thumbnail_url.clone(),
             Some(selected.video_id.clone()),
-            src,
+            String::new(),
         );
 
         if is_queue {
             queue_add_front(new_track);
             refresh_ui(None);
         } else {
+            let source = match resolve::stream_url(&new_track, true).await {
+                Ok(source) => source,
+                Err(e) => {
+                    set_status_line(Some(format!(":( {}", e)));
+                    return Ok(());
+                }
+            };
             if let Some(track) = current_track {
-                add_to_history(track.clone());
+                add_to_history(track.clone(), true);
                 player::stop_process(currently_playing, &track.title, music_dir);
             }
 
@@ -919,7 +1409,7 @@ pub async fn handle_song_selection(
             }
             ui_common::clear_lyrics();
             *current_track = Some(new_track.clone());
-            *currently_playing = Some(player::play_file(&new_track.url, &new_track, &music_dir)?);
+            *currently_playing = Some(player::play_file(&source, &new_track, music_dir)?);
 
             if !config().no_autoplay {
                 let yt = yt_client.clone();
@@ -945,7 +1435,7 @@ async fn handle_library_browsing(
     yt_client: &api::YTMusic,
     music_dir: &std::path::PathBuf,
     current_track: &mut Option<Track>,
-    currently_playing: &mut Option<Child>,
+    currently_playing: &mut Option<player::Playback>,
 ) -> Result<(), Box<dyn std::error::Error>> {
     set_status_line(Some("Fetching Library...".to_string()));
 
@@ -972,14 +1462,14 @@ async fn handle_library_browsing(
     *LIBRARY_SONG_LIST.write().unwrap() = initial_songs;
 
     let mut page: usize = 1;
-    const PAGE_SIZE: usize = 5;
+    let page_size = config().ui.library_page_size;
 
     loop {
         refresh_ui(None);
 
         let list_len = LIBRARY_SONG_LIST.read().unwrap().len();
-        let start = (page - 1) * PAGE_SIZE;
-        let end = std::cmp::min(start + PAGE_SIZE, list_len);
+        let start = (page - 1) * page_size;
+        let end = std::cmp::min(start + page_size, list_len);
 
         println!(" [n]ext | [p]rev | [s]huffle");
         if start < list_len {
@@ -993,7 +1483,7 @@ async fn handle_library_browsing(
         if let Ok(input) = rx.recv() {
             match input.trim() {
                 "n" => {
-                    if page * PAGE_SIZE >= list_len {
+                    if page * page_size >= list_len {
                         if let Some(token) = continuation_token.take() {
                             set_status_line(Some("Fetching more...".into()));
 
@@ -1043,7 +1533,7 @@ async fn handle_library_browsing(
 
                 num_str => {
                     if let Ok(num) = num_str.parse::<usize>() {
-                        let song_idx = (page - 1) * PAGE_SIZE + (num - 1);
+                        let song_idx = (page - 1) * page_size + (num - 1);
                         let song = LIBRARY_SONG_LIST.read().unwrap().get(song_idx).cloned();
 
                         if let Some(s) = song {
@@ -1066,6 +1556,236 @@ async fn handle_library_browsing(
     }
     Ok(())
 }
+// n / p cycle through the windows, esc leaves
+fn handle_stats_screen(rx: &std::sync::mpsc::Receiver<String>) {
+    let entries = history::load_all();
+    let top = config().stats.top;
+    let start = stats::parse_window(&config().stats.window).unwrap_or(stats::Window::Days(30));
+    let mut windows: Vec<stats::Window> = stats::SCREEN_WINDOWS.to_vec();
+    if !windows.contains(&start) {
+        windows.insert(0, start);
+    }
+    let mut idx = windows.iter().position(|w| *w == start).unwrap_or(0);
+
+    loop {
+        refresh_ui(None);
+
+        let window = windows[idx];
+        let s = stats::compute(&entries, window.range(), top);
+        print!("\r\n");
+        for line in stats::render_lines(&s, &window.label()) {
+            print!("\r\x1b[2K {}\r\n", line);
+        }
+        let _ = stdout().flush();
+        set_status_line(Some("[n]ext | [p]rev window".to_string()));
+
+        match rx.recv().as_deref() {
+            Ok("n") => idx = (idx + 1) % windows.len(),
+            Ok("p") => idx = (idx + windows.len() - 1) % windows.len(),
+            Ok("" | "S") | Err(_) => break,
+            _ => {}
+        }
+    }
+}
+
+// digit = switch to that device, n / p page through long lists, esc leaves
+fn handle_device_picker(rx: &std::sync::mpsc::Receiver<String>) {
+    set_status_line(Some("Looking for devices...".to_string()));
+    let devices = output::devices();
+    let page_size = 9;
+    let mut page = 0;
+
+    loop {
+        refresh_ui(None);
+
+        let current = output::current();
+        let start = page * page_size;
+        let end = std::cmp::min(start + page_size, devices.len());
+        print!(
+            "\r\x1b[2K\n{}\r\n",
+            "--- AUDIO OUTPUT ---".bold().underline()
+        );
+        for (i, (name, description)) in devices[start..end].iter().enumerate() {
+            let line = format!("{}. {} {}", i + 1, description.bold(), format!("[{}]", name).cyan());
+            if *name == current {
+                print!("\r\x1b[2K{} {}\r\n", line, "<".green().bold());
+            } else {
+                print!("\r\x1b[2K{}\r\n", line);
+            }
+        }
+        let _ = stdout().flush();
+        set_status_line(Some("[n]ext | [p]rev | pick 1-9".to_string()));
+
+        match rx.recv().as_deref() {
+            Ok("n") if end < devices.len() => page += 1,
+            Ok("p") if page > 0 => page -= 1,
+            Ok("" | "o") | Err(_) => break,
+            Ok(sel) => {
+                let picked = sel
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|n| *n >= 1)
+                    .and_then(|n| devices[start..end].get(n - 1));
+                if let Some((name, description)) = picked {
+                    match output::set(name) {
+                        Ok(()) => set_status_line(Some(format!("AUDIO: {}", description))),
+                        Err(e) => set_status_line(Some(format!(":( {}", e))),
+                    }
+                    break;
+                }
+            }
+        }
+    }
+}
+
+// full history from history.jsonl, newest first
+// digit = play it now, qN = put it at the front of the queue
+async fn handle_history_browsing(
+    rx: &std::sync::mpsc::Receiver<String>,
+    yt_client: &api::YTMusic,
+    music_dir: &std::path::PathBuf,
+    current_track: &mut Option<Track>,
+    currently_playing: &mut Option<player::Playback>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut entries = history::load_all();
+    entries.reverse();
+
+    if entries.is_empty() {
+        set_status_line(Some("No history yet".to_string()));
+        return Ok(());
+    }
+
+    let mut page: usize = 1;
+    let page_size = config().ui.library_page_size;
+    let pages = entries.len().div_ceil(page_size);
+
+    loop {
+        refresh_ui(None);
+
+        let start = (page - 1) * page_size;
+        let end = std::cmp::min(start + page_size, entries.len());
+
+        println!(" [n]ext | [p]rev | alt+<N> to queue");
+        ui_common::show_history(&entries[start..end]);
+        set_status_line(Some(format!(
+            "Page {}/{} | {} plays",
+            page,
+            pages,
+            entries.len()
+        )));
+
+        let Ok(input) = rx.recv() else {
+            break;
+        };
+        let input = input.trim().to_string();
+        match input.as_str() {
+            "n" => {
+                if page < pages {
+                    page += 1;
+                }
+            }
+            "p" => {
+                if page > 1 {
+                    page -= 1;
+                }
+            }
+            // q closes the browser, quitting takes a second q
+            "" | "r" | "q" => break,
+            other => {
+                let (is_queue, num_str) = match other.strip_prefix('q') {
+                    Some(n) => (true, n),
+                    None => (false, other),
+                };
+                let Ok(num) = num_str.parse::<usize>() else {
+                    continue;
+                };
+                let Some(entry) = num
+                    .checked_sub(1)
+                    .filter(|i| start + i < end)
+                    .and_then(|i| entries.get(start + i))
+                    .cloned()
+                else {
+                    continue;
+                };
+
+                if replay_history_entry(
+                    &entry,
+                    is_queue,
+                    music_dir,
+                    yt_client,
+                    current_track,
+                    currently_playing,
+                )
+                .await?
+                {
+                    break;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+// returns false if the entry can't be played anymore (file gone, offline)
+async fn replay_history_entry(
+    entry: &history::HistoryEntry,
+    is_queue: bool,
+    music_dir: &std::path::PathBuf,
+    yt_client: &api::YTMusic,
+    current_track: &mut Option<Track>,
+    currently_playing: &mut Option<player::Playback>,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    // prefer the cached copy, it needs no network
+    if entry.local_file_exists() {
+        let track = entry.to_track();
+        if is_queue {
+            queue_add_front(track);
+            set_status_line(Some(format!("Queued {}", entry.title)));
+            return Ok(true);
+        }
+
+        if let Some(old) = current_track {
+            add_to_history(old.clone(), true);
+            player::stop_process(currently_playing, &old.title, music_dir);
+        }
+        ui_common::clear_lyrics();
+        *current_track = Some(track.clone());
+        *currently_playing = Some(player::play_file(&track.url, &track, music_dir)?);
+        refresh_ui(Some(&track));
+        return Ok(true);
+    }
+
+    let Some(video_id) = entry.video_id.clone() else {
+        set_status_line(Some(format!("{} is no longer on disk", entry.title)));
+        return Ok(false);
+    };
+    if config().offline_mode {
+        set_status_line(Some("Not cached, can't play offline".to_string()));
+        return Ok(false);
+    }
+
+    let song = api::SongDetails {
+        title: entry.title.clone(),
+        artists: entry.artists.clone(),
+        album: entry.album.clone(),
+        duration: entry.duration.clone(),
+        thumbnail_url: entry.thumbnail_url.clone(),
+        video_id,
+    };
+    let choice = if is_queue { "q1" } else { "1" };
+    handle_song_selection(
+        choice.to_string(),
+        &[song],
+        music_dir,
+        yt_client,
+        current_track,
+        currently_playing,
+        None,
+    )
+    .await?;
+    Ok(true)
+}
+
 /// -------------------------------------------------------------------
 /// QUEUE & MPV IPC & PLAYBACK
 /// -------------------------------------------------------------------
@@ -1091,14 +1811,32 @@ fn refresh_ui(track_details: Option<&Track>) {
     }
 }
 
-fn add_to_history(track: Track) {
+fn ask_resume(rx: &std::sync::mpsc::Receiver<String>, track: &Track, position: f64) -> bool {
+    let pos = position.max(0.0) as u64;
+    print!(
+        "\n\n\n\r  {}\n\r  {} [{}] at {}:{:02}\n\r  1) Resume\n\r  2) Start fresh\n\r",
+        "--- CONTINUE WHERE YOU LEFT OFF? ---".bold(),
+        track.title,
+        track.artists.join(", ").dimmed(),
+        pos / 60,
+        pos % 60
+    );
+    let _ = stdout().flush();
+
+    matches!(rx.recv().as_deref(), Ok("1"))
+}
+
+// skipped = user moved on before mpv reached the end
+fn add_to_history(track: Track, skipped: bool) {
+    history::finish(skipped);
+
     let mut list = RECENTLY_PLAYED.write().unwrap();
     if let Some(last) = list.back() {
         if last.title == track.title {
             return;
         }
     }
-    if list.len() >= HISTORY_LIMIT {
+    if list.len() >= config().playback.history_limit {
         list.pop_front();
     }
     list.push_back(track);
@@ -1128,6 +1866,59 @@ fn queue_next() -> Option<Track> {
     }
 }
 
+// plays the first queued track that can be played (after `event` if the
+// backend may have moved on by itself), the ones whose stream can't be found are skipped
+async fn play_next_queued(
+    event: Option<&PlayerEvent>,
+    music_dir: &PathBuf,
+) -> Option<(Track, player::Playback)> {
+    while let Some(track) = queue_next() {
+        if let Some(reason) = failover::marked(&track) {
+            set_status_line(Some(format!(":( Skipped {}: {}", track.title, reason)));
+            continue;
+        }
+        let result = match event {
+            Some(event) => player::continue_with(event, &track, music_dir).await,
+            None => player::play_track(&track, music_dir, None).await,
+        };
+        match result {
+            Ok(p) => return Some((track, p)),
+            Err(e) => {
+                let reason = net::explain(&e.to_string());
+                failover::mark(&track, &reason);
+                set_status_line(Some(format!(":( Skipped {}: {}", track.title, reason)));
+            }
+        }
+    }
+    None
+}
+
+// plays `track` again after `failure` of `failed` at `position`, where the
+// backend saw it stop: the same source with a fresh url when that may help,
+// otherwise the next source that plays. also returns what to put on the status line
+async fn recover_playback(
+    track: &Track,
+    failure: failover::Failure,
+    reason: &str,
+    mut failed: String,
+    position: Option<f64>,
+    music_dir: &PathBuf,
+) -> Option<(player::Playback, String)> {
+    if failure.is_transient()
+        && resolve::retry_allowed(track)
+        && let Ok(p) = player::play_track(track, music_dir, position).await
+    {
+        return Some((p, "STREAM RECONNECTED".to_string()));
+    }
+    loop {
+        let (source, via) = resolve::next_source(track, &failed).await.ok()?;
+        match player::play_file_at(&source, track, music_dir, position) {
+            Ok(p) => return Some((p, format!(":( {}, trying {}", reason, via.label()))),
+            Err(_) => failed = source,
+        }
+    }
+}
+
 fn if_title_contains_non_english_and_other_language_script_return_only_english_part(
     title: &str,
 ) -> String {
@@ -1141,7 +1932,7 @@ fn if_title_contains_non_english_and_other_language_script_return_only_english_p
 pub async fn queue_auto_add_online(yt: api::YTMusic, id: String) {
     let needs_songs = {
         let q = SONG_QUEUE.read().unwrap();
-        q.len() < 2
+        q.len() < config().autoplay.refill_below
     };
 
     if needs_songs {
@@ -1163,7 +1954,12 @@ pub async fn queue_auto_add_online(yt: api::YTMusic, id: String) {
             };
 
             if let Ok(related) = yt
-                .fetch_related_songs(&id, playlist_id.as_deref(), 50, should_suffle)
+                .fetch_related_songs(
+                    &id,
+                    playlist_id.as_deref(),
+                    config().autoplay.related_fetch,
+                    should_suffle,
+                )
                 .await
             {
                 let mut c = RELATED_SONG_LIST.write().unwrap();
@@ -1177,7 +1973,7 @@ pub async fn queue_auto_add_online(yt: api::YTMusic, id: String) {
         let mut to_fetch = Vec::new();
         {
             let mut c = RELATED_SONG_LIST.write().unwrap();
-            for _ in 0..5 {
+            for _ in 0..config().autoplay.batch_size {
                 if !c.is_empty() {
                     let item = c.remove(0);
                     to_fetch.push(item);
@@ -1187,35 +1983,17 @@ pub async fn queue_auto_add_online(yt: api::YTMusic, id: String) {
             }
         }
 
-        // Resolve stream URLs and add to queue
+        // streams are resolved when each one comes up, see resolve.rs
         for details in to_fetch {
-            let mut final_url = None;
-
-            // Check lossless if enabled
-            if config().lossless_mode {
-                let clean_title =
-                    if_title_contains_non_english_and_other_language_script_return_only_english_part(
-                        &details.title,
-                    );
-                let query = format!("{} {}", clean_title, details.artists.join(" "));
-                final_url = fetch_flac_stream_url(&query, &details.duration).await.ok();
-            }
-
-            if final_url.is_none() {
-                final_url = yt.fetch_stream_url(&details.video_id).await.ok();
-            }
-
-            if let Some(url) = final_url {
-                queue_add(Track::new(
-                    details.title,
-                    details.artists,
-                    details.album,
-                    details.duration,
-                    details.thumbnail_url,
-                    Some(details.video_id),
-                    url,
-                ));
-            }
+            queue_add(Track::new(
+                details.title,
+                details.artists,
+                details.album,
+                details.duration,
+                details.thumbnail_url,
+                Some(details.video_id),
+                String::new(),
+            ));
         }
 
         refresh_ui(None);
@@ -1227,19 +2005,53 @@ pub fn config() -> &'static AppConfig {
     CONFIG.get().expect("Config is not initialized")
 }
 
-use crossterm::event::{KeyCode, KeyEventKind};
+use crate::keymap::{Action, keymap};
+use crossterm::event::{
+    KeyCode, KeyEventKind, KeyboardEnhancementFlags, PopKeyboardEnhancementFlags,
+    PushKeyboardEnhancementFlags,
+};
 use std::io::{self, Write};
 use std::sync::mpsc::Sender;
+
+// set while the kitty keyboard flags are pushed, they have to be popped on the way out
+static KEYBOARD_ENHANCED: AtomicBool = AtomicBool::new(false);
+
+// hands the terminal back the way we found it. every way out goes through
+// here: q, whytui ctl / mpris quit and panics
+pub fn restore_terminal() {
+    if KEYBOARD_ENHANCED.swap(false, Ordering::SeqCst) {
+        let _ = execute!(io::stdout(), PopKeyboardEnhancementFlags);
+    }
+    let _ = execute!(io::stdout(), crossterm::cursor::Show);
+    let _ = crossterm::terminal::disable_raw_mode();
+}
+
 pub fn spawn_input_handler(tx: Sender<String>) {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        restore_terminal();
+        default_hook(info);
+    }));
+
     std::thread::spawn(move || {
         let _ = crossterm::terminal::enable_raw_mode();
+        // lets terminals with the kitty protocol report media keys and more chords
+        if matches!(crossterm::terminal::supports_keyboard_enhancement(), Ok(true)) {
+            let _ = execute!(
+                io::stdout(),
+                PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES)
+            );
+            KEYBOARD_ENHANCED.store(true, Ordering::SeqCst);
+        }
 
         loop {
             if let Ok(true) = event::poll(std::time::Duration::from_millis(100)) {
                 if let Ok(ev) = event::read() {
                     match ev {
-                        Event::Key(key) if key.kind == KeyEventKind::Press => match key.code {
-                            KeyCode::Char('/') => {
+                        Event::Key(key) if key.kind == KeyEventKind::Press => match keymap()
+                            .lookup(&key)
+                        {
+                            Some(Action::Search) => {
                                 execute!(io::stdout(), crossterm::cursor::Show).ok();
                                 let mut query = String::new();
                                 let prompt = "> ".bright_blue().bold();
@@ -1276,95 +2088,25 @@ pub fn spawn_input_handler(tx: Sender<String>) {
                                 }
                             }
 
-                            KeyCode::Esc => {
+                            Some(Action::Refresh) => {
                                 let _ = tx.send("".to_string());
                                 let _ = tx.send("REFRESH_UI".into());
                             }
 
-                            KeyCode::Char(c) if c.is_ascii_digit() => {
-                                let _ = tx.send(c.to_string());
-                            }
-
-                            KeyCode::Char('L') => {
-                                let _ = tx.send("L".into());
-                            }
-                            KeyCode::Char('l') => {
-                                let _ = tx.send("l".into());
-                            }
-                            KeyCode::Char('a') => {
-                                let _ = tx.send("a".into());
-                            }
-                            KeyCode::Char('u') => {
-                                let _ = tx.send("u".into());
-                            }
-                            KeyCode::Char(' ') => {
-                                let _ = tx.send("pause".into());
-                            }
-                            KeyCode::Char('p') => {
-                                let _ = tx.send("p".into());
-                            }
-                            KeyCode::Char('n') => {
-                                let _ = tx.send("n".into());
-                            }
-                            KeyCode::Char('v') => {
-                                let _ = tx.send("v".into());
-                            }
-                            KeyCode::Char('t') => {
-                                let _ = tx.send("t".into());
-                            }
-                            KeyCode::Char('r') => {
-                                let _ = tx.send("r".into());
-                            }
-                            KeyCode::Char('R') => {
-                                let _ = tx.send("R".into());
-                            }
-                            // KeyCode::Char('s') => {
-                            //     let _ = tx.send("s".into());
-                            // }
-                            KeyCode::Char('g') => {
-                                let _ = tx.send("g".into());
-                            }
-
-                            KeyCode::Char('+') | KeyCode::Char('=') => {
-                                let _ = tx.send("+".into());
-                            }
-                            KeyCode::Char('-') => {
-                                let _ = tx.send("-".into());
-                            }
-                            KeyCode::Char('[') => {
-                                let _ = tx.send("[".into());
-                            }
-                            KeyCode::Char(']') => {
-                                let _ = tx.send("]".into());
-                            }
-                            KeyCode::Right => {
-                                let _ = tx.send(">5".into());
-                            }
-                            KeyCode::Left => {
-                                let _ = tx.send("<5".into());
+                            Some(action) => {
+                                if let Some(cmd) = action.command() {
+                                    let _ = tx.send(cmd);
+                                }
                             }
 
-                            KeyCode::Char('q') => {
-                                let _ = crossterm::terminal::disable_raw_mode();
-                                let _ = tx.send("q".into());
-                                return;
-                            }
-                            KeyCode::Char('!') => {
-                                let _ = tx.send("q1".into());
-                            }
-                            KeyCode::Char('@') => {
-                                let _ = tx.send("q2".into());
-                            }
-                            KeyCode::Char('#') => {
-                                let _ = tx.send("q3".into());
-                            }
-                            KeyCode::Char('$') | KeyCode::Char('€') => {
-                                let _ = tx.send("q4".into());
-                            }
-                            KeyCode::Char('%') => {
-                                let _ = tx.send("q5".into());
+                            // unbound digits pick songs/playlists from lists
+                            None => {
+                                if let KeyCode::Char(c) = key.code
+                                    && c.is_ascii_digit()
+                                {
+                                    let _ = tx.send(c.to_string());
+                                }
                             }
-                            _ => {}
                         },
 
                         Event::Resize(_, _) => {
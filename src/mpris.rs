@@ -0,0 +1,384 @@
+use crate::{Track, config, player};
+use std::collections::HashMap;
+use std::sync::atomic::Ordering;
+use std::sync::mpsc::Sender;
+use std::time::Duration;
+use zbus::object_server::SignalEmitter;
+use zbus::zvariant::{ObjectPath, OwnedValue, Value};
+use zbus::{connection, interface};
+
+// -------------------------------------------------------------------
+// org.mpris.MediaPlayer2 on the session bus so playerctl, media keys
+// and desktop widgets can drive whytui. every method just sends the
+// same string a key press would (on the remote channel, see control.rs),
+// the main loop does the real work
+// -------------------------------------------------------------------
+
+const OBJECT_PATH: &str = "/org/mpris/MediaPlayer2";
+const BUS_NAME: &str = "org.mpris.MediaPlayer2.whytui";
+
+struct Root {
+    tx: Sender<String>,
+}
+
+struct MprisPlayer {
+    tx: Sender<String>,
+}
+
+#[derive(Clone, PartialEq, Default)]
+struct Snapshot {
+    track_key: Option<String>,
+    paused: bool,
+    volume: i64,
+    repeat: usize,
+}
+
+fn snapshot() -> Snapshot {
+    let track = crate::history::current_track();
+    Snapshot {
+        track_key: track.map(|t| format!("{}{:?}{}", t.title, t.video_id, t.url)),
+        paused: player::is_paused(),
+        volume: crate::VOLUME.load(Ordering::Relaxed),
+        repeat: crate::REPEAT_MODE.load(Ordering::Relaxed),
+    }
+}
+
+fn micros(secs: f64) -> i64 {
+    (secs.max(0.0) * 1_000_000.0) as i64
+}
+
+fn track_id(track: &Track) -> ObjectPath<'static> {
+    // object paths only allow [A-Za-z0-9_]
+    let id: String = track
+        .video_id
+        .clone()
+        .unwrap_or_else(|| track.title.clone())
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    ObjectPath::try_from(format!("/org/whytui/track/t{}", id))
+        .unwrap_or_else(|_| ObjectPath::from_static_str_unchecked("/org/whytui/track/current"))
+}
+
+fn owned<'a>(v: impl Into<Value<'a>>) -> Option<OwnedValue> {
+    OwnedValue::try_from(v.into()).ok()
+}
+
+fn metadata() -> HashMap<String, OwnedValue> {
+    let mut map = HashMap::new();
+    let Some(track) = crate::history::current_track() else {
+        if let Some(v) = owned(ObjectPath::from_static_str_unchecked(
+            "/org/mpris/MediaPlayer2/TrackList/NoTrack",
+        )) {
+            map.insert("mpris:trackid".to_string(), v);
+        }
+        return map;
+    };
+
+    let length = crate::ui_common::duration_to_seconds(&track.duration);
+    let entries: Vec<(&str, Option<OwnedValue>)> = vec![
+        ("mpris:trackid", owned(track_id(&track))),
+        ("mpris:length", owned(micros(length))),
+        ("xesam:title", owned(track.title.clone())),
+        ("xesam:artist", owned(track.artists.clone())),
+        ("xesam:album", owned(track.album.clone())),
+        (
+            "mpris:artUrl",
+            track.thumbnail_url.clone().and_then(owned),
+        ),
+        (
+            "xesam:url",
+            track
+                .video_id
+                .as_ref()
+                .map(|id| format!("https://music.youtube.com/watch?v={}", id))
+                .and_then(owned),
+        ),
+    ];
+    for (key, value) in entries {
+        if let Some(v) = value {
+            map.insert(key.to_string(), v);
+        }
+    }
+    map
+}
+
+#[interface(name = "org.mpris.MediaPlayer2")]
+impl Root {
+    fn raise(&self) {}
+
+    fn quit(&self) {
+        // "q" puts the terminal back before exiting
+        let _ = self.tx.send("q".to_string());
+    }
+
+    #[zbus(property)]
+    fn can_quit(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_raise(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn has_track_list(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn identity(&self) -> String {
+        "whytui".to_string()
+    }
+
+    #[zbus(property)]
+    fn supported_uri_schemes(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    #[zbus(property)]
+    fn supported_mime_types(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+#[interface(name = "org.mpris.MediaPlayer2.Player")]
+impl MprisPlayer {
+    fn next(&self) {
+        let _ = self.tx.send("n".to_string());
+    }
+
+    fn previous(&self) {
+        let _ = self.tx.send("p".to_string());
+    }
+
+    fn pause(&self) {
+        if !player::is_paused() {
+            let _ = self.tx.send("pause".to_string());
+        }
+    }
+
+    fn play(&self) {
+        if player::is_paused() {
+            let _ = self.tx.send("pause".to_string());
+        }
+    }
+
+    fn play_pause(&self) {
+        let _ = self.tx.send("pause".to_string());
+    }
+
+    // there is no real stop, pausing is the closest thing
+    fn stop(&self) {
+        self.pause();
+    }
+
+    async fn seek(&self, offset: i64, #[zbus(signal_emitter)] emitter: SignalEmitter<'_>) {
+        let secs = offset / 1_000_000;
+        if secs == 0 {
+            return;
+        }
+        let cmd = if secs > 0 {
+            format!(">{}", secs)
+        } else {
+            format!("<{}", -secs)
+        };
+        let _ = self.tx.send(cmd);
+
+        let pos = player::get_time_info().map(|(p, _)| p).unwrap_or(0.0);
+        let _ = Self::seeked(&emitter, micros(pos + secs as f64)).await;
+    }
+
+    async fn set_position(
+        &self,
+        _track_id: ObjectPath<'_>,
+        position: i64,
+        #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+    ) {
+        let Some((pos, total)) = player::get_time_info() else {
+            return;
+        };
+        let target = position as f64 / 1_000_000.0;
+        if target < 0.0 || target > total {
+            return;
+        }
+        let delta = (target - pos).round() as i64;
+        let cmd = if delta >= 0 {
+            format!(">{}", delta)
+        } else {
+            format!("<{}", -delta)
+        };
+        let _ = self.tx.send(cmd);
+        let _ = Self::seeked(&emitter, position).await;
+    }
+
+    fn open_uri(&self, _uri: String) -> zbus::fdo::Result<()> {
+        Err(zbus::fdo::Error::NotSupported(
+            "use the search prompt instead".to_string(),
+        ))
+    }
+
+    #[zbus(signal)]
+    async fn seeked(emitter: &SignalEmitter<'_>, position: i64) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn playback_status(&self) -> String {
+        if crate::history::current_track().is_none() {
+            "Stopped"
+        } else if player::is_paused() {
+            "Paused"
+        } else {
+            "Playing"
+        }
+        .to_string()
+    }
+
+    #[zbus(property)]
+    fn loop_status(&self) -> String {
+        if crate::REPEAT_MODE.load(Ordering::Relaxed) == 0 {
+            "None"
+        } else {
+            "Track"
+        }
+        .to_string()
+    }
+
+    #[zbus(property)]
+    fn rate(&self) -> f64 {
+        crate::tempo::speed()
+    }
+
+    #[zbus(property)]
+    fn set_rate(&mut self, value: f64) {
+        // the spec says 0 is pause, not a speed
+        if value > 0.0 {
+            let _ = self.tx.send(format!("speed={}", value));
+        }
+    }
+
+    #[zbus(property)]
+    fn minimum_rate(&self) -> f64 {
+        crate::tempo::MIN_SPEED
+    }
+
+    #[zbus(property)]
+    fn maximum_rate(&self) -> f64 {
+        crate::tempo::MAX_SPEED
+    }
+
+    #[zbus(property)]
+    fn shuffle(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn metadata(&self) -> HashMap<String, OwnedValue> {
+        metadata()
+    }
+
+    // mpris volume is 0.0..1.0 (more = amplified), whytui uses mpv's 0..max_volume
+    #[zbus(property)]
+    fn volume(&self) -> f64 {
+        crate::VOLUME.load(Ordering::Relaxed) as f64 / 100.0
+    }
+
+    #[zbus(property)]
+    fn set_volume(&mut self, value: f64) {
+        let target = (value * 100.0).round() as i64;
+        let _ = self
+            .tx
+            .send(format!("vol={}", target.clamp(0, config().playback.max_volume)));
+    }
+
+    #[zbus(property(emits_changed_signal = "false"))]
+    fn position(&self) -> i64 {
+        micros(player::get_time_info().map(|(p, _)| p).unwrap_or(0.0))
+    }
+
+    #[zbus(property)]
+    fn can_go_next(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_go_previous(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_play(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_pause(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_seek(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_control(&self) -> bool {
+        true
+    }
+}
+
+// runs for the whole session, a missing session bus just disables mpris
+pub fn spawn(tx: Sender<String>) {
+    if !config().remote.mpris {
+        return;
+    }
+    tokio::spawn(async move {
+        let _ = serve(tx).await;
+    });
+}
+
+async fn serve(tx: Sender<String>) -> zbus::Result<()> {
+    let conn = connection::Builder::session()?
+        .serve_at(OBJECT_PATH, Root { tx: tx.clone() })?
+        .serve_at(OBJECT_PATH, MprisPlayer { tx })?
+        .build()
+        .await?;
+
+    // a second whytui gets its own name instead of failing
+    if conn.request_name(BUS_NAME).await.is_err() {
+        conn.request_name(format!("{}.instance{}", BUS_NAME, std::process::id()))
+            .await?;
+    }
+
+    let iface = conn
+        .object_server()
+        .interface::<_, MprisPlayer>(OBJECT_PATH)
+        .await?;
+
+    // state lives in globals and mpv, so poll it and announce what changed
+    let mut last = snapshot();
+    loop {
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        let now = snapshot();
+        if now == last {
+            continue;
+        }
+
+        let player = iface.get().await;
+        let emitter = iface.signal_emitter();
+        if now.track_key != last.track_key {
+            let _ = player.metadata_changed(emitter).await;
+            let _ = player.playback_status_changed(emitter).await;
+        }
+        if now.paused != last.paused {
+            let _ = player.playback_status_changed(emitter).await;
+        }
+        if now.volume != last.volume {
+            let _ = player.volume_changed(emitter).await;
+        }
+        if now.repeat != last.repeat {
+            let _ = player.loop_status_changed(emitter).await;
+        }
+        last = now;
+    }
+}
@@ -0,0 +1,430 @@
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::mpsc::{UnboundedSender, unbounded_channel};
+
+// -------------------------------------------------------------------
+// one long-lived connection to mpv's json ipc. a reader task answers
+// requests by request_id, keeps the observed properties cached (so the
+// ui can ask for the position every tick for free) and turns end-file /
+// start-file / idle into events for the backend
+// -------------------------------------------------------------------
+
+// properties we observe, the number is the observe id mpv echoes back
+const OBSERVED: [(u64, &str); 6] = [
+    (1, "time-pos"),
+    (2, "duration"),
+    (3, "pause"),
+    (4, "volume"),
+    (5, "idle-active"),
+    (6, "audio-device-list"),
+];
+
+const REPLY_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Default)]
+pub struct Props {
+    pub time_pos: Option<f64>,
+    pub duration: Option<f64>,
+    pub pause: bool,
+    pub volume: Option<f64>,
+    pub idle: bool,
+    // (name, description) of every output mpv can use, None until it said
+    pub devices: Option<Vec<(String, String)>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum IpcEvent {
+    // playback ran out (eof or error) and mpv went idle. position is the
+    // last real (time-pos, duration), mpv has cleared both by now
+    Ended {
+        error: Option<String>,
+        position: Option<(f64, f64)>,
+    },
+    // the file ended and mpv started the next playlist entry by itself
+    NextStarted,
+    // the socket closed, mpv is gone
+    Closed,
+}
+
+#[derive(Default)]
+struct Shared {
+    props: Props,
+    // set by end-file (eof/error), resolved by the next start-file or idle
+    ended: Option<Option<String>>,
+    // time-pos and duration of the current file, the nulls at its end skipped
+    last_pos: Option<f64>,
+    last_duration: Option<f64>,
+}
+
+impl Shared {
+    fn position(&self) -> Option<(f64, f64)> {
+        Some((self.last_pos?, self.last_duration.unwrap_or(0.0)))
+    }
+}
+
+pub struct MpvIpc {
+    out: UnboundedSender<String>,
+    pending: Arc<Mutex<HashMap<u64, Sender<Value>>>>,
+    next_id: AtomicU64,
+    shared: Arc<Mutex<Shared>>,
+    events: Mutex<Receiver<IpcEvent>>,
+    alive: Arc<AtomicBool>,
+}
+
+impl MpvIpc {
+    // must run inside the tokio runtime, the reader/writer are tasks on it
+    pub fn connect(path: &str) -> Result<MpvIpc, Box<dyn std::error::Error>> {
+        #[cfg(unix)]
+        {
+            let std_stream = std::os::unix::net::UnixStream::connect(path)?;
+            std_stream.set_nonblocking(true)?;
+            let stream = tokio::net::UnixStream::from_std(std_stream)?;
+            let (read, write) = tokio::io::split(stream);
+            Ok(Self::start(read, write))
+        }
+        #[cfg(windows)]
+        {
+            let pipe = tokio::net::windows::named_pipe::ClientOptions::new().open(path)?;
+            let (read, write) = tokio::io::split(pipe);
+            Ok(Self::start(read, write))
+        }
+    }
+
+    fn start<R, W>(read: R, mut write: W) -> MpvIpc
+    where
+        R: AsyncRead + Unpin + Send + 'static,
+        W: AsyncWrite + Unpin + Send + 'static,
+    {
+        let (out, mut out_rx) = unbounded_channel::<String>();
+        let (event_tx, event_rx) = mpsc::channel();
+        let pending: Arc<Mutex<HashMap<u64, Sender<Value>>>> = Arc::default();
+        let shared: Arc<Mutex<Shared>> = Arc::default();
+        let alive = Arc::new(AtomicBool::new(true));
+
+        tokio::spawn(async move {
+            while let Some(line) = out_rx.recv().await {
+                if write.write_all(line.as_bytes()).await.is_err() {
+                    break;
+                }
+                let _ = write.flush().await;
+            }
+        });
+
+        {
+            let pending = pending.clone();
+            let shared = shared.clone();
+            let alive = alive.clone();
+            tokio::spawn(async move {
+                let mut lines = BufReader::new(read).lines();
+                while let Ok(Some(line)) = lines.next_line().await {
+                    let Ok(msg) = serde_json::from_str::<Value>(&line) else {
+                        continue;
+                    };
+                    handle_message(msg, &pending, &shared, &event_tx);
+                }
+                alive.store(false, Ordering::SeqCst);
+                // wake anyone still waiting for a reply
+                pending.lock().unwrap().clear();
+                let _ = event_tx.send(IpcEvent::Closed);
+            });
+        }
+
+        let ipc = MpvIpc {
+            out,
+            pending,
+            next_id: AtomicU64::new(100),
+            shared,
+            events: Mutex::new(event_rx),
+            alive,
+        };
+        for (id, name) in OBSERVED {
+            ipc.send(json!(["observe_property", id, name]));
+        }
+        ipc
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.alive.load(Ordering::SeqCst)
+    }
+
+    // fire and forget
+    pub fn send(&self, args: Value) {
+        let _ = self.out.send(format!("{}\n", json!({ "command": args })));
+    }
+
+    // waits for mpv's answer, Err carries mpv's error string
+    pub fn command(&self, args: Value) -> Result<Value, String> {
+        if !self.is_alive() {
+            return Err("mpv is not running".to_string());
+        }
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (tx, rx) = mpsc::channel();
+        self.pending.lock().unwrap().insert(id, tx);
+
+        let msg = json!({ "command": args, "request_id": id });
+        if self.out.send(format!("{}\n", msg)).is_err() {
+            self.pending.lock().unwrap().remove(&id);
+            return Err("mpv is not running".to_string());
+        }
+
+        let reply = rx.recv_timeout(REPLY_TIMEOUT);
+        self.pending.lock().unwrap().remove(&id);
+        let reply = reply.map_err(|_| "mpv is not responding".to_string())?;
+        match reply["error"].as_str() {
+            Some("success") => Ok(reply["data"].clone()),
+            Some(e) => Err(format!("mpv: {}", e)),
+            None => Err("mpv sent garbage".to_string()),
+        }
+    }
+
+    pub fn props(&self) -> Props {
+        self.shared.lock().unwrap().props.clone()
+    }
+
+    // where the current file got to, still there after it ended or mpv died
+    pub fn last_position(&self) -> Option<(f64, f64)> {
+        self.shared.lock().unwrap().position()
+    }
+
+    pub fn next_event(&self) -> Option<IpcEvent> {
+        self.events.lock().unwrap().try_recv().ok()
+    }
+}
+
+fn handle_message(
+    msg: Value,
+    pending: &Mutex<HashMap<u64, Sender<Value>>>,
+    shared: &Mutex<Shared>,
+    events: &Sender<IpcEvent>,
+) {
+    if let Some(id) = msg["request_id"].as_u64() {
+        if let Some(tx) = pending.lock().unwrap().remove(&id) {
+            let _ = tx.send(msg);
+        }
+        return;
+    }
+
+    let mut sh = shared.lock().unwrap();
+    match msg["event"].as_str() {
+        Some("property-change") => {
+            let data = &msg["data"];
+            match msg["name"].as_str() {
+                Some("time-pos") => {
+                    sh.props.time_pos = data.as_f64();
+                    sh.last_pos = data.as_f64().or(sh.last_pos);
+                }
+                Some("duration") => {
+                    sh.props.duration = data.as_f64();
+                    sh.last_duration = data.as_f64().or(sh.last_duration);
+                }
+                Some("pause") => sh.props.pause = data.as_bool().unwrap_or(false),
+                Some("volume") => sh.props.volume = data.as_f64(),
+                Some("audio-device-list") => sh.props.devices = parse_devices(data),
+                Some("idle-active") => {
+                    sh.props.idle = data.as_bool().unwrap_or(false);
+                    if sh.props.idle
+                        && let Some(error) = sh.ended.take()
+                    {
+                        let position = sh.position();
+                        let _ = events.send(IpcEvent::Ended { error, position });
+                    }
+                }
+                _ => {}
+            }
+        }
+        // reason is eof, error, stop (our own replace/stop), quit or redirect
+        Some("end-file") => match msg["reason"].as_str() {
+            Some("eof") | Some("error") => {
+                let error = (msg["reason"] == "error").then(|| {
+                    msg["file_error"]
+                        .as_str()
+                        .unwrap_or("playback failed")
+                        .to_string()
+                });
+                if sh.props.idle {
+                    let position = sh.position();
+                    let _ = events.send(IpcEvent::Ended { error, position });
+                } else {
+                    sh.ended = Some(error);
+                }
+            }
+            _ => sh.ended = None,
+        },
+        Some("start-file") => {
+            if sh.ended.take().is_some() {
+                let _ = events.send(IpcEvent::NextStarted);
+            }
+            sh.props.time_pos = None;
+            sh.props.duration = None;
+            sh.last_pos = None;
+            sh.last_duration = None;
+        }
+        _ => {}
+    }
+}
+
+// [{"name":"pulse/alsa_output...","description":"Speakers"}, ...]
+pub fn parse_devices(data: &Value) -> Option<Vec<(String, String)>> {
+    let list = data.as_array()?;
+    Some(
+        list.iter()
+            .filter_map(|d| {
+                let name = d["name"].as_str()?.to_string();
+                let description = d["description"].as_str().unwrap_or(&name).to_string();
+                Some((name, description))
+            })
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // feeds recorded mpv lines in order, returns what the backend would see
+    fn feed(shared: &Mutex<Shared>, lines: &[&str]) -> Vec<IpcEvent> {
+        let pending = Mutex::new(HashMap::new());
+        let (tx, rx) = mpsc::channel();
+        for line in lines {
+            handle_message(serde_json::from_str(line).unwrap(), &pending, shared, &tx);
+        }
+        rx.try_iter().collect()
+    }
+
+    fn playing() -> Mutex<Shared> {
+        let shared = Mutex::new(Shared::default());
+        let events = feed(
+            &shared,
+            &[
+                r#"{"event":"property-change","id":5,"name":"idle-active","data":false}"#,
+                r#"{"event":"start-file","playlist_entry_id":1}"#,
+                r#"{"event":"property-change","id":2,"name":"duration","data":212.06}"#,
+                r#"{"event":"property-change","id":1,"name":"time-pos","data":211.9}"#,
+            ],
+        );
+        assert!(events.is_empty());
+        shared
+    }
+
+    #[test]
+    fn eof_then_idle_is_a_clean_end() {
+        let shared = playing();
+        let events = feed(
+            &shared,
+            &[
+                r#"{"event":"end-file","reason":"eof","playlist_entry_id":1}"#,
+                r#"{"event":"property-change","id":1,"name":"time-pos","data":null}"#,
+                r#"{"event":"idle"}"#,
+                r#"{"event":"property-change","id":5,"name":"idle-active","data":true}"#,
+            ],
+        );
+        assert_eq!(
+            events,
+            vec![IpcEvent::Ended {
+                error: None,
+                position: Some((211.9, 212.06))
+            }]
+        );
+        let sh = shared.lock().unwrap();
+        assert!(sh.props.idle);
+        assert_eq!(sh.props.time_pos, None);
+        assert!(sh.ended.is_none());
+    }
+
+    #[test]
+    fn error_carries_file_error() {
+        let shared = playing();
+        let events = feed(
+            &shared,
+            &[
+                r#"{"event":"end-file","reason":"error","playlist_entry_id":1,"file_error":"loading failed"}"#,
+                r#"{"event":"property-change","id":5,"name":"idle-active","data":true}"#,
+            ],
+        );
+        assert_eq!(
+            events,
+            vec![IpcEvent::Ended {
+                error: Some("loading failed".to_string()),
+                position: Some((211.9, 212.06))
+            }]
+        );
+        assert!(shared.lock().unwrap().props.idle);
+    }
+
+    #[test]
+    fn stop_from_our_own_replace_is_silent() {
+        let shared = playing();
+        let events = feed(
+            &shared,
+            &[
+                r#"{"event":"end-file","reason":"stop","playlist_entry_id":1}"#,
+                r#"{"event":"start-file","playlist_entry_id":2}"#,
+                r#"{"event":"property-change","id":1,"name":"time-pos","data":0.0}"#,
+            ],
+        );
+        assert!(events.is_empty());
+        let sh = shared.lock().unwrap();
+        assert!(!sh.props.idle);
+        assert_eq!(sh.props.time_pos, Some(0.0));
+        assert_eq!(sh.props.duration, None);
+        assert_eq!(sh.position(), Some((0.0, 0.0)));
+        assert!(sh.ended.is_none());
+    }
+
+    #[test]
+    fn gapless_start_after_eof_is_next_started() {
+        let shared = playing();
+        let events = feed(
+            &shared,
+            &[
+                r#"{"event":"end-file","reason":"eof","playlist_entry_id":1}"#,
+                r#"{"event":"start-file","playlist_entry_id":2}"#,
+            ],
+        );
+        assert_eq!(events, vec![IpcEvent::NextStarted]);
+        {
+            let sh = shared.lock().unwrap();
+            assert!(!sh.props.idle);
+            assert_eq!(sh.props.time_pos, None);
+            assert_eq!(sh.props.duration, None);
+            assert_eq!(sh.position(), None);
+            assert!(sh.ended.is_none());
+        }
+        // the next song playing on must not read as an end later
+        let events = feed(
+            &shared,
+            &[r#"{"event":"property-change","id":1,"name":"time-pos","data":1.5}"#],
+        );
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn a_stream_that_played_out_is_no_failure() {
+        let shared = playing();
+        let events = feed(
+            &shared,
+            &[
+                r#"{"event":"end-file","reason":"eof","playlist_entry_id":1}"#,
+                r#"{"event":"property-change","id":1,"name":"time-pos","data":null}"#,
+                r#"{"event":"property-change","id":2,"name":"duration","data":null}"#,
+                r#"{"event":"property-change","id":5,"name":"idle-active","data":true}"#,
+            ],
+        );
+        let [IpcEvent::Ended { error, position }] = events.as_slice() else {
+            panic!("expected one end, got {:?}", events);
+        };
+        // youtube's length, and one a bit longer than the stream itself
+        for length in ["3:32", "3:50"] {
+            let track = crate::Track {
+                duration: length.to_string(),
+                ..crate::Track::sample(Some("abc"), "")
+            };
+            assert_eq!(crate::failover::classify(&track, error.as_deref(), *position), None);
+        }
+    }
+}
@@ -0,0 +1,167 @@
+use crate::config;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::PathBuf;
+
+// -------------------------------------------------------------------
+// [network] in config.toml: the cookie jar, proxy and user agent that
+// whytui's own requests use, handed the same way to every tool it runs
+// (yt-dlp, mpv, ffmpeg; cookies to the first two only), plus extra
+// arguments per tool. without the cookies, age-restricted, members-only
+// and "not a bot" checks fail
+// -------------------------------------------------------------------
+
+// what the web player sends, googlevideo is pickier with anything else
+pub const DEFAULT_USER_AGENT: &str = "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/142.0.0.0 Safari/537.36,gzip(gfe)";
+
+pub struct Cookie {
+    pub domain: String,
+    pub path: String,
+    pub name: String,
+    pub value: String,
+}
+
+// network.cookies, or cookies.txt in the config dir
+pub fn cookies_file() -> Option<PathBuf> {
+    let configured = &config().network.cookies;
+    if configured.as_os_str().is_empty() {
+        Some(crate::settings::config_dir()?.join("cookies.txt"))
+    } else {
+        Some(configured.clone())
+    }
+}
+
+// netscape format: domain, subdomains, path, secure, expiry, name, value
+// (tab separated), #HttpOnly_ lines are cookies too
+pub fn read_cookies() -> Vec<Cookie> {
+    let Some(file) = cookies_file().and_then(|p| File::open(p).ok()) else {
+        return Vec::new();
+    };
+    BufReader::new(file)
+        .lines()
+        .map_while(Result::ok)
+        .filter_map(|line| {
+            let line = line.trim();
+            let line = line.strip_prefix("#HttpOnly_").unwrap_or(line);
+            if line.is_empty() || line.starts_with('#') {
+                return None;
+            }
+            let parts: Vec<&str> = line.split('\t').collect();
+            if parts.len() < 7 {
+                return None;
+            }
+            Some(Cookie {
+                domain: parts[0].trim().to_string(),
+                path: parts[2].trim().to_string(),
+                name: parts[5].trim().to_string(),
+                value: parts[6].trim().to_string(),
+            })
+        })
+        .collect()
+}
+
+// the jar is only worth passing on when something is in it
+fn cookies_for_tools() -> Option<String> {
+    if read_cookies().is_empty() {
+        return None;
+    }
+    Some(cookies_file()?.to_string_lossy().to_string())
+}
+
+pub fn proxy() -> Option<&'static str> {
+    Some(config().network.proxy.as_str()).filter(|p| !p.is_empty())
+}
+
+pub fn user_agent() -> &'static str {
+    let ua = config().network.user_agent.as_str();
+    if ua.is_empty() { DEFAULT_USER_AGENT } else { ua }
+}
+
+// reqwest clients of our own go through the same proxy
+pub fn client_builder() -> reqwest::ClientBuilder {
+    let builder = reqwest::Client::builder();
+    match proxy().and_then(|p| reqwest::Proxy::all(p).ok()) {
+        Some(p) => builder.proxy(p),
+        None => builder,
+    }
+}
+
+pub fn blocking_client_builder() -> reqwest::blocking::ClientBuilder {
+    let builder = reqwest::blocking::Client::builder();
+    match proxy().and_then(|p| reqwest::Proxy::all(p).ok()) {
+        Some(p) => builder.proxy(p),
+        None => builder,
+    }
+}
+
+// ---- PER TOOL ----
+
+pub fn ytdlp_args() -> Vec<String> {
+    let mut args = Vec::new();
+    if let Some(jar) = cookies_for_tools() {
+        args.extend(["--cookies".to_string(), jar]);
+    }
+    if let Some(p) = proxy() {
+        args.extend(["--proxy".to_string(), p.to_string()]);
+    }
+    args.extend(["--user-agent".to_string(), user_agent().to_string()]);
+    args.extend(config().network.ytdlp_args.iter().cloned());
+    args
+}
+
+// urls from the native resolver override the user agent per file, see
+// resolve::user_agent_for
+pub fn mpv_args() -> Vec<String> {
+    let mut args = vec![format!("--user-agent={}", user_agent())];
+    if let Some(jar) = cookies_for_tools() {
+        args.push("--cookies=yes".to_string());
+        args.push(format!("--cookies-file={}", jar));
+    }
+    if let Some(p) = proxy() {
+        args.push(format!("--http-proxy={}", p));
+    }
+    args.extend(config().network.mpv_args.iter().cloned());
+    args
+}
+
+// goes before -i. no cookies: ffmpeg only takes them inline, where every
+// process on the machine can read them, and the signed stream urls it
+// downloads don't need them
+pub fn ffmpeg_input_args() -> Vec<String> {
+    let mut args = vec!["-user_agent".to_string(), user_agent().to_string()];
+    if let Some(p) = proxy() {
+        args.extend(["-http_proxy".to_string(), p.to_string()]);
+    }
+    args.extend(config().network.ffmpeg_args.iter().cloned());
+    args
+}
+
+// ---- ERRORS ----
+
+// a tool's complaint, with a hint when it's one cookies would fix
+pub fn explain(error: &str) -> String {
+    let lower = error.to_lowercase();
+    let wants_login = ["sign in", "confirm your age", "not a bot", "members", "login", "private video"]
+        .iter()
+        .any(|k| lower.contains(k));
+    if wants_login && read_cookies().is_empty() {
+        format!("{} (needs cookies.txt)", error)
+    } else if wants_login {
+        format!("{} (cookies.txt expired?)", error)
+    } else {
+        error.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ffmpeg_never_gets_the_cookie_jar() {
+        let _ = crate::CONFIG.set(crate::settings::AppConfig::default());
+        let args = ffmpeg_input_args();
+        assert_eq!(args[..2], ["-user_agent".to_string(), user_agent().to_string()]);
+        assert!(!args.iter().any(|a| a == "-cookies" || a.contains("domain=")));
+    }
+}
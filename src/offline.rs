@@ -7,8 +7,9 @@ pub fn populate_queue_offline(
     queue: &mut Vec<Track>,
     exclude_titles: &[String],
 ) {
-    if queue.len() < 3 {
-        let new_songs = get_random_batch(music_dir, exclude_titles, 5);
+    let autoplay = &crate::config().autoplay;
+    if queue.len() < autoplay.offline_refill_below {
+        let new_songs = get_random_batch(music_dir, exclude_titles, autoplay.offline_batch_size);
         for song in new_songs {
             queue.push(song);
         }
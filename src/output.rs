@@ -0,0 +1,83 @@
+use crate::{config, player};
+use std::sync::RwLock;
+
+// -------------------------------------------------------------------
+// which sound card / sink mpv plays on. a device set in [playback]
+// audio_device is used on every start; left at "auto", the last one
+// picked with `o` (saved in config/audio_device) is. the backend falls
+// back to the system default while it's unplugged
+// -------------------------------------------------------------------
+
+pub const DEFAULT_DEVICE: &str = "auto";
+
+static CURRENT: RwLock<String> = RwLock::new(String::new());
+
+fn saved_path() -> Option<std::path::PathBuf> {
+    Some(crate::settings::config_dir()?.join("audio_device"))
+}
+
+pub fn current() -> String {
+    CURRENT.read().unwrap().clone()
+}
+
+// the device to start on: one set in config.toml, else the last pick
+fn startup_device(configured: &str, saved: Option<String>) -> String {
+    if configured != DEFAULT_DEVICE {
+        return configured.to_string();
+    }
+    saved.unwrap_or_else(|| DEFAULT_DEVICE.to_string())
+}
+
+// called once on startup, before anything plays
+pub fn init() {
+    let saved = saved_path()
+        .and_then(|p| std::fs::read_to_string(p).ok())
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty());
+    let name = startup_device(&config().playback.audio_device, saved);
+    player::set_audio_device(&name);
+    *CURRENT.write().unwrap() = name;
+}
+
+// (name, description), the system default first
+pub fn devices() -> Vec<(String, String)> {
+    let mut list = player::audio_devices();
+    if !list.iter().any(|(name, _)| name == DEFAULT_DEVICE) {
+        list.insert(0, (DEFAULT_DEVICE.to_string(), "System default".to_string()));
+    }
+    list
+}
+
+// description of a device for the status line, the name if it's unknown
+pub fn describe(name: &str) -> String {
+    devices()
+        .into_iter()
+        .find(|(n, _)| n == name)
+        .map(|(_, d)| d)
+        .unwrap_or_else(|| name.to_string())
+}
+
+pub fn set(name: &str) -> Result<(), String> {
+    if !devices().iter().any(|(n, _)| n == name) {
+        return Err(format!("no audio device '{}'", name));
+    }
+    player::set_audio_device(name);
+    *CURRENT.write().unwrap() = name.to_string();
+    if let Some(path) = saved_path() {
+        let _ = std::fs::write(path, name);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn configured_device_wins_over_the_saved_pick() {
+        let saved = || Some("pulse/headphones".to_string());
+        assert_eq!(startup_device("alsa/hdmi", saved()), "alsa/hdmi");
+        assert_eq!(startup_device("auto", saved()), "pulse/headphones");
+        assert_eq!(startup_device("auto", None), "auto");
+    }
+}
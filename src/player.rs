@@ -1,52 +1,125 @@
 use crate::Track;
+use crate::backend::{PlayerEvent, backend};
 use crate::config;
 use lofty::picture::{MimeType, Picture, PictureType};
 use lofty::prelude::*;
 use lofty::tag::Tag;
-use serde_json::json;
 use std::fs::{self, File};
-use std::io::{BufRead, BufReader, Write};
 use std::path::PathBuf;
-use std::process::{Child, Command, Stdio};
+use std::process::{Command, Stdio};
 use std::sync::atomic::Ordering;
 use std::time::Duration;
 
-#[cfg(target_os = "windows")]
-use std::os::windows::process::CommandExt;
+// the track the backend has loaded, returned by play_file
+pub struct Playback {
+    // what the backend is playing, follows gapless switches
+    source: String,
+}
+
+impl Playback {
+    pub fn poll(&mut self) -> Option<PlayerEvent> {
+        let event = backend().poll_event();
+        if let Some(PlayerEvent::Advanced { source }) = &event {
+            self.source = source.clone();
+        }
+        event
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+}
 
 pub fn play_file(
     source: &str,
-    _track: &Track,
+    track: &Track,
+    music_dir: &PathBuf,
+) -> Result<Playback, Box<dyn std::error::Error>> {
+    play_file_at(source, track, music_dir, None)
+}
+
+// same as play_file but starts at `start` seconds (used to resume a session)
+pub fn play_file_at(
+    source: &str,
+    track: &Track,
     _music_dir: &PathBuf,
-) -> Result<Child, Box<dyn std::error::Error>> {
-    let ipc = get_ipc_path();
-    let current_vol = crate::VOLUME.load(Ordering::Relaxed);
+    start: Option<f64>,
+) -> Result<Playback, Box<dyn std::error::Error>> {
+    let current_vol = crate::timer::scaled_volume(crate::VOLUME.load(Ordering::Relaxed));
+
+    crate::loudness::prepare(source);
+    crate::tempo::prepare(track);
+    backend().load(source, track, start, current_vol)?;
+    track_started(source, track);
+    Ok(Playback {
+        source: source.to_string(),
+    })
+}
 
+// bookkeeping for a track that just started, loaded by us or switched to by the backend
+fn track_started(source: &str, track: &Track) {
     crate::IS_PLAYING.store(true, Ordering::SeqCst);
 
-    #[cfg(unix)]
-    let _ = std::fs::remove_file(&ipc);
-
     if source.contains(".tidal") || source.ends_with(".flac") || source.ends_with(".mpd") {
         crate::PLAYING_LOSSLESS.store(true, Ordering::SeqCst);
     } else {
         crate::PLAYING_LOSSLESS.store(false, Ordering::SeqCst);
     }
-let user_agent = "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/142.0.0.0 Safari/537.36,gzip(gfe)";
-    let mut cmd = Command::new("mpv");
-    cmd.arg("--no-video")
-        .arg("--really-quiet")
-        .arg("--force-window=no")
-        .arg(format!("--input-ipc-server={}", ipc))
-        .arg(format!("--volume={}", current_vol))
-        .arg("--demuxer-lavf-o=protocol_whitelist=[file,http,https,tcp,tls,crypto,data]")
-        .arg(format!("--user-agent={}", user_agent))
-        .arg("--http-header-fields=Referer: https://music.youtube.com/,Origin: https://music.youtube.com")
-        .arg(source)
-        .stdout(Stdio::null())
-        .stderr(Stdio::null());
+    crate::quality::set_playing(crate::resolve::info_of(source));
+    crate::failover::note_started();
+    crate::history::begin(track, source);
+}
 
-    Ok(cmd.spawn()?)
+// resolves the stream of `track` (unless a fresh one is cached) and plays it
+pub async fn play_track(
+    track: &Track,
+    music_dir: &PathBuf,
+    start: Option<f64>,
+) -> Result<Playback, Box<dyn std::error::Error>> {
+    let source = crate::resolve::stream_url(track, false)
+        .await
+        .map_err(|e| e.to_string())?;
+    play_file_at(&source, track, music_dir, start)
+}
+
+// plays `track` after `event`, unless the backend already switched to it gaplessly
+pub async fn continue_with(
+    event: &PlayerEvent,
+    track: &Track,
+    music_dir: &PathBuf,
+) -> Result<Playback, Box<dyn std::error::Error>> {
+    if let PlayerEvent::Advanced { source } = event
+        && crate::resolve::is_source_of(track, source)
+    {
+        crate::loudness::prepare(source);
+        crate::tempo::prepare(track);
+        track_started(source, track);
+        return Ok(Playback {
+            source: source.clone(),
+        });
+    }
+    play_track(track, music_dir, None).await
+}
+
+// hands the backend the track that should follow the one playing `current`.
+// one that isn't resolved yet gets resolved in the background and preloaded
+// next time, data saver fetches nothing ahead
+pub fn preload(current: &str, next: Option<&Track>) {
+    if crate::quality::data_saver() {
+        backend().preload(None);
+        return;
+    }
+    let ready = next.and_then(|t| match crate::resolve::peek(t) {
+        // it would start under the current track's normaliser, so it is
+        // loaded fresh when its turn comes
+        Some(source) if !crate::loudness::can_follow(current, &source) => None,
+        Some(source) => Some((source, t)),
+        None => {
+            crate::resolve::prefetch(t);
+            None
+        }
+    });
+    backend().preload(ready.as_ref().map(|(source, t)| (source.as_str(), *t)));
 }
 
 pub fn background_download(
@@ -64,9 +137,7 @@ pub fn background_download(
     let file_name =
         the_naming_format_in_which_i_have_saved_the_track_locally(&track.title, &track.artists);
 
-    let temp_path = music_dir
-        .join("temp")
-        .join(format!("{}.{}", file_name, ext));
+    let temp_path = crate::instance::temp_dir().join(format!("{}.{}", file_name, ext));
     let final_path = music_dir.join(format!("{}.{}", file_name, ext));
 
     if final_path.exists() {
@@ -81,6 +152,7 @@ pub fn background_download(
         .arg("-y")
         .arg("-protocol_whitelist")
         .arg("file,http,https,tcp,tls,crypto,data")
+        .args(crate::net::ffmpeg_input_args())
         .arg("-i")
         .arg(source)
         .arg("-vn")
@@ -115,8 +187,14 @@ pub fn background_download(
         tag.set_album(track.album.clone());
     }
 
+    if config().loudness.tag_downloads
+        && let Some(analysis) = crate::loudness::analyze(&temp_path)
+    {
+        crate::loudness::write_tags(tag, &analysis);
+    }
+
     if let Some(url) = &track.thumbnail_url {
-        let client = reqwest::blocking::Client::builder()
+        let client = crate::net::blocking_client_builder()
             .timeout(Duration::from_secs(10))
             .build()?;
 
@@ -134,33 +212,45 @@ pub fn background_download(
 
     tag.save_to_path(&temp_path, lofty::config::WriteOptions::default())?;
 
-    std::fs::rename(&temp_path, &final_path)?;
+    // the runtime dir is usually a tmpfs, rename can't cross filesystems.
+    // copy next to the final file first so it never shows up half written
+    if std::fs::rename(&temp_path, &final_path).is_err() {
+        let part_path = music_dir.join(format!(".{}.{}.part", file_name, ext));
+        let moved = std::fs::copy(&temp_path, &part_path)
+            .and_then(|_| std::fs::rename(&part_path, &final_path));
+        let _ = std::fs::remove_file(&temp_path);
+        if let Err(e) = moved {
+            let _ = std::fs::remove_file(&part_path);
+            return Err(e.into());
+        }
+    }
 
     Ok(())
 }
 
-pub fn stop_process(proc: &mut Option<Child>, _song_name: &str, _music_dir: &PathBuf) {
+pub fn stop_process(proc: &mut Option<Playback>, _song_name: &str, _music_dir: &PathBuf) {
     crate::IS_PLAYING.store(false, Ordering::SeqCst);
 
-    if let Some(mut child) = proc.take() {
-        #[cfg(target_os = "windows")]
-        {
-            let _ = Command::new("taskkill")
-                .args(["/F", "/T", "/PID", &child.id().to_string()])
-                .creation_flags(0x08000000)
-                .output();
+    if proc.take().is_some() {
+        backend().stop();
+    }
+}
+
+pub fn music_dir_path() -> Option<PathBuf> {
+    match &config().music_dir {
+        Some(d) => Some(d.clone()),
+        None => {
+            let mut d = dirs::audio_dir()?;
+            d.push("whytui");
+            Some(d)
         }
-        let _ = child.kill();
-        let _ = child.wait();
     }
 }
 
 pub fn prepare_music_dir() -> Result<PathBuf, Box<dyn std::error::Error>> {
-    let mut d = dirs::audio_dir().ok_or("No audio dir")?;
-    d.push("whytui");
+    let d = music_dir_path().ok_or("No audio dir")?;
     fs::create_dir_all(&d)?;
-    fs::create_dir_all(d.join("temp"))?;
-    let config_dir = d.join("config");
+    let config_dir = crate::settings::config_dir().ok_or("No audio dir")?;
     fs::create_dir_all(&config_dir)?;
     let cookies_path = config_dir.join("cookies.txt");
     if !cookies_path.exists() {
@@ -169,84 +259,59 @@ pub fn prepare_music_dir() -> Result<PathBuf, Box<dyn std::error::Error>> {
     Ok(d)
 }
 
+// only leftovers of dead instances, a running one keeps its downloads
 pub fn clear_temp(music_dir: &PathBuf) {
-    let temp_dir = music_dir.join("temp");
-    if let Ok(entries) = std::fs::read_dir(&temp_dir) {
-        for entry in entries {
-            if let Ok(entry) = entry {
-                let path = entry.path();
-                if path.is_file() {
-                    let _ = std::fs::remove_file(path);
-                }
-            }
-        }
-    }
+    crate::instance::clear_stale();
+    // older versions kept a shared temp dir in the music dir
+    let _ = std::fs::remove_dir_all(music_dir.join("temp"));
 }
 
-pub fn get_ipc_path() -> String {
-    if cfg!(unix) {
-        "/tmp/whytui.sock".to_string()
-    } else {
-        r"\\.\pipe\whytui.sock".to_string()
-    }
+pub fn set_filter(label: &str, graph: Option<&str>) {
+    backend().set_filter(label, graph);
 }
 
-pub fn send_ipc(cmd: serde_json::Value) -> Option<String> {
-    let path = get_ipc_path();
-    let msg = format!("{}\n", cmd.to_string());
+pub fn audio_devices() -> Vec<(String, String)> {
+    backend().audio_devices()
+}
 
-    #[cfg(unix)]
-    {
-        use std::os::unix::net::UnixStream;
-        if let Ok(mut stream) = UnixStream::connect(&path) {
-            let _ = stream.write_all(msg.as_bytes());
-            let _ = stream.flush();
-            let mut reader = BufReader::new(&stream);
-            let mut resp = String::new();
-            stream
-                .set_read_timeout(Some(Duration::from_millis(200)))
-                .ok();
-            if reader.read_line(&mut resp).is_ok() {
-                return Some(resp);
-            }
-        }
-    }
+pub fn set_audio_device(name: &str) {
+    backend().set_audio_device(name);
+}
 
-    #[cfg(windows)]
-    {
-        use std::fs::OpenOptions;
-        if let Ok(mut file) = OpenOptions::new().read(true).write(true).open(&path) {
-            let _ = file.write_all(msg.as_bytes());
-            let _ = file.flush();
-            let mut reader = BufReader::new(&file);
-            let mut resp = String::new();
-            if reader.read_line(&mut resp).is_ok() {
-                return Some(resp);
-            }
-        }
-    }
-    None
+pub fn set_speed(speed: f64) {
+    backend().set_speed(speed);
 }
 
 pub fn get_time_info() -> Option<(f64, f64)> {
-    let get = |p| {
-        send_ipc(json!({"command": ["get_property", p]}))
-            .and_then(|s| serde_json::from_str::<serde_json::Value>(&s).ok())
-            .and_then(|v| v["data"].as_f64())
-    };
-    Some((get("time-pos")?, get("duration")?))
+    backend().position()
+}
+
+// false when nothing is playing
+pub fn is_paused() -> bool {
+    backend().is_paused()
 }
 
 pub fn toggle_pause() {
-    send_ipc(json!({"command": ["cycle", "pause"]}));
+    backend().toggle_pause();
 }
 
 pub fn seek(s: i64) {
-    send_ipc(json!({"command": ["seek", s, "relative"]}));
+    backend().seek(s);
+}
+
+// `v` is the user's volume, a sleep fade or wake-up ramp scales it down
+pub fn set_volume(v: i64) {
+    backend().set_volume(crate::timer::scaled_volume(v));
+}
+
+pub fn stop() {
+    crate::IS_PLAYING.store(false, Ordering::SeqCst);
+    backend().stop();
 }
 
-pub fn vol_change(s: i64) {
-    send_ipc(json!({ "command": ["add", "volume", s] }));
+// kills the player process, only for quitting
+pub fn shutdown() {
+    backend().shutdown();
 }
 
 pub fn the_naming_format_in_which_i_have_saved_the_track_locally(
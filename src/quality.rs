@@ -0,0 +1,309 @@
+use crate::api::AudioFormat;
+use crate::config;
+use crate::settings::StreamConfig;
+use lofty::prelude::*;
+use std::sync::RwLock;
+
+// -------------------------------------------------------------------
+// which youtube audio format gets streamed and downloaded ([stream] in
+// config.toml, optionally per [stream.profiles.<name>]) and what the
+// playing one actually is, for the status line. data saver caps the
+// bitrate, skips lossless and stops fetching the next song ahead of time
+// -------------------------------------------------------------------
+
+// opus high to low, then aac
+pub const FORMATS: &[&str] = &["best", "251", "250", "249", "140"];
+
+// nominal values for when only the url (and its itag) is known, e.g. from yt-dlp
+const KNOWN_ITAGS: &[(i64, &str, u32, u32)] = &[
+    (774, "OPUS", 256, 48000),
+    (251, "OPUS", 160, 48000),
+    (250, "OPUS", 70, 48000),
+    (249, "OPUS", 50, 48000),
+    (141, "AAC", 256, 44100),
+    (140, "AAC", 128, 44100),
+    (139, "AAC", 48, 22050),
+];
+
+struct State {
+    profile: String,
+    // flipped at runtime, None = what the config says
+    data_saver: Option<bool>,
+}
+
+static STATE: RwLock<State> = RwLock::new(State {
+    profile: String::new(),
+    data_saver: None,
+});
+
+static PLAYING: RwLock<Option<StreamInfo>> = RwLock::new(None);
+
+// what the active settings ask for
+pub struct Quality {
+    pub itag: Option<i64>,
+    // kbps, 0 = no cap
+    pub max_kbps: u32,
+    pub data_saver: bool,
+}
+
+pub fn is_format(s: &str) -> bool {
+    FORMATS.contains(&s)
+}
+
+// called once on startup
+pub fn init() {
+    STATE.write().unwrap().profile = config().stream.profile.clone();
+}
+
+pub fn profile() -> String {
+    STATE.read().unwrap().profile.clone()
+}
+
+pub fn current() -> Quality {
+    let st = STATE.read().unwrap();
+    quality_of(&config().stream, &st.profile, st.data_saver)
+}
+
+// [stream] with `profile` laid over it and the runtime data saver switch on top
+fn quality_of(s: &StreamConfig, profile: &str, data_saver: Option<bool>) -> Quality {
+    let profile = s.profiles.get(profile);
+    let format = profile
+        .and_then(|p| p.format.as_deref())
+        .unwrap_or(&s.format);
+    let mut max_kbps = profile.and_then(|p| p.max_kbps).unwrap_or(s.max_kbps);
+    let data_saver = data_saver
+        .or_else(|| profile.and_then(|p| p.data_saver))
+        .unwrap_or(s.data_saver);
+    if data_saver && (max_kbps == 0 || max_kbps > s.data_saver_kbps) {
+        max_kbps = s.data_saver_kbps;
+    }
+    Quality {
+        itag: format.parse().ok(),
+        max_kbps,
+        data_saver,
+    }
+}
+
+pub fn data_saver() -> bool {
+    current().data_saver
+}
+
+// "" (or "default") goes back to the plain [stream] settings
+pub fn set_profile(name: &str) -> Result<(), String> {
+    let name = if name == "default" { "" } else { name };
+    if !name.is_empty() && !config().stream.profiles.contains_key(name) {
+        return Err(format!("No [stream.profiles.{}] in config.toml", name));
+    }
+    let mut st = STATE.write().unwrap();
+    st.profile = name.to_string();
+    st.data_saver = None;
+    Ok(())
+}
+
+pub fn toggle_data_saver() -> bool {
+    let on = !data_saver();
+    STATE.write().unwrap().data_saver = Some(on);
+    on
+}
+
+// the format to stream out of what /player offered (best first)
+pub fn pick(formats: &[AudioFormat]) -> Option<&AudioFormat> {
+    pick_for(&current(), formats)
+}
+
+fn pick_for<'a>(q: &Quality, formats: &'a [AudioFormat]) -> Option<&'a AudioFormat> {
+    let fits = |f: &&AudioFormat| q.max_kbps == 0 || f.bitrate <= q.max_kbps as i64 * 1000;
+    q.itag
+        .and_then(|itag| formats.iter().filter(fits).find(|f| f.itag == itag))
+        .or_else(|| formats.iter().find(fits))
+        // nothing under the cap, the smallest there is
+        .or_else(|| formats.last())
+}
+
+// the same choice as a yt-dlp -f selector
+pub fn ytdlp_selector() -> String {
+    selector_for(&current())
+}
+
+fn selector_for(q: &Quality) -> String {
+    let (cap, best) = if q.max_kbps > 0 {
+        let cap = format!("[abr<={}]", q.max_kbps);
+        let best = format!("bestaudio{}/worstaudio", cap);
+        (cap, best)
+    } else {
+        (String::new(), "bestaudio".to_string())
+    };
+    match q.itag {
+        Some(itag) => format!("{}{}/{}", itag, cap, best),
+        None => best,
+    }
+}
+
+// ---- WHAT IS PLAYING ----
+
+#[derive(Debug, Clone)]
+pub struct StreamInfo {
+    pub codec: String,
+    pub kbps: u32,
+    pub sample_rate: Option<u32>,
+}
+
+impl StreamInfo {
+    pub fn from_format(f: &AudioFormat) -> Self {
+        // audio/webm; codecs="opus" / audio/mp4; codecs="mp4a.40.2"
+        let codec = if f.mime_type.contains("opus") {
+            "OPUS"
+        } else if f.mime_type.contains("mp4a") {
+            "AAC"
+        } else {
+            "AUDIO"
+        };
+        Self {
+            codec: codec.to_string(),
+            kbps: (f.bitrate as f64 / 1000.0).round() as u32,
+            sample_rate: f.sample_rate,
+        }
+    }
+
+    // googlevideo urls name their itag
+    pub fn from_url(url: &str) -> Option<Self> {
+        let itag: i64 = url
+            .split_once('?')?
+            .1
+            .split('&')
+            .find_map(|kv| kv.strip_prefix("itag="))?
+            .parse()
+            .ok()?;
+        let (_, codec, kbps, rate) = KNOWN_ITAGS.iter().find(|k| k.0 == itag)?;
+        Some(Self {
+            codec: codec.to_string(),
+            kbps: *kbps,
+            sample_rate: Some(*rate),
+        })
+    }
+
+    pub fn from_file(path: &str) -> Option<Self> {
+        let tagged = lofty::read_from_path(path).ok()?;
+        let props = tagged.properties();
+        let codec = std::path::Path::new(path)
+            .extension()?
+            .to_string_lossy()
+            .to_uppercase();
+        Some(Self {
+            codec,
+            kbps: props.audio_bitrate()?,
+            sample_rate: props.sample_rate(),
+        })
+    }
+
+    // OPUS • 130 KBPS • 48 KHZ
+    pub fn label(&self) -> String {
+        let mut label = format!("{} • {} KBPS", self.codec, self.kbps);
+        if let Some(rate) = self.sample_rate {
+            let khz = rate as f64 / 1000.0;
+            if khz.fract() == 0.0 {
+                label.push_str(&format!(" • {} KHZ", khz));
+            } else {
+                label.push_str(&format!(" • {:.1} KHZ", khz));
+            }
+        }
+        label
+    }
+}
+
+pub fn set_playing(info: Option<StreamInfo>) {
+    *PLAYING.write().unwrap() = info;
+}
+
+pub fn playing() -> Option<StreamInfo> {
+    PLAYING.read().unwrap().clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::{AppConfig, StreamProfile};
+
+    fn offer(itag: i64, kbps: i64) -> AudioFormat {
+        AudioFormat {
+            itag,
+            mime_type: "audio/webm; codecs=\"opus\"".to_string(),
+            bitrate: kbps * 1000,
+            sample_rate: Some(48000),
+            url: format!("https://x.test/{}", itag),
+        }
+    }
+
+    // what /player offers, best first
+    fn offered() -> Vec<AudioFormat> {
+        vec![offer(251, 139), offer(140, 129), offer(250, 70), offer(249, 51)]
+    }
+
+    fn quality(itag: Option<i64>, max_kbps: u32) -> Quality {
+        Quality {
+            itag,
+            max_kbps,
+            data_saver: false,
+        }
+    }
+
+    fn picked(q: Quality) -> Option<i64> {
+        pick_for(&q, &offered()).map(|f| f.itag)
+    }
+
+    #[test]
+    fn picks_the_asked_format_under_the_cap() {
+        assert_eq!(picked(quality(None, 0)), Some(251));
+        assert_eq!(picked(quality(Some(140), 0)), Some(140));
+        assert_eq!(picked(quality(None, 100)), Some(250));
+        // the asked one is over the cap: the best one under it
+        assert_eq!(picked(quality(Some(251), 100)), Some(250));
+        // not offered at all
+        assert_eq!(picked(quality(Some(774), 0)), Some(251));
+        // nothing fits, the smallest
+        assert_eq!(picked(quality(None, 32)), Some(249));
+        assert!(pick_for(&quality(None, 0), &[]).is_none());
+    }
+
+    #[test]
+    fn builds_matching_ytdlp_selectors() {
+        assert_eq!(selector_for(&quality(None, 0)), "bestaudio");
+        assert_eq!(selector_for(&quality(Some(251), 0)), "251/bestaudio");
+        assert_eq!(
+            selector_for(&quality(None, 64)),
+            "bestaudio[abr<=64]/worstaudio"
+        );
+        assert_eq!(
+            selector_for(&quality(Some(140), 64)),
+            "140[abr<=64]/bestaudio[abr<=64]/worstaudio"
+        );
+    }
+
+    #[test]
+    fn profiles_and_data_saver_layer_over_stream() {
+        let mut s = AppConfig::default().stream;
+        s.format = "251".to_string();
+        s.data_saver_kbps = 64;
+        s.profiles.insert(
+            "mobile".to_string(),
+            StreamProfile {
+                format: Some("250".to_string()),
+                max_kbps: Some(96),
+                data_saver: None,
+            },
+        );
+
+        let q = quality_of(&s, "", None);
+        assert_eq!((q.itag, q.max_kbps, q.data_saver), (Some(251), 0, false));
+        let q = quality_of(&s, "mobile", None);
+        assert_eq!((q.itag, q.max_kbps), (Some(250), 96));
+        // data saver only ever lowers the cap
+        let q = quality_of(&s, "mobile", Some(true));
+        assert_eq!((q.max_kbps, q.data_saver), (64, true));
+        s.data_saver_kbps = 128;
+        assert_eq!(quality_of(&s, "mobile", Some(true)).max_kbps, 96);
+        // an unknown profile is plain [stream], "best" is no itag
+        s.format = "best".to_string();
+        assert_eq!(quality_of(&s, "gone", None).itag, None);
+    }
+}
@@ -0,0 +1,425 @@
+use crate::flac::fetch_flac_stream_url;
+use crate::api::AudioFormat;
+use crate::history::{self, PlaySource};
+use crate::quality::{self, StreamInfo};
+use crate::settings::StreamResolver;
+use crate::ui_common::{duration_to_seconds, set_status_line};
+use crate::{Track, api, config, player};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+// -------------------------------------------------------------------
+// stream urls are looked up right before a track plays, not when it is
+// queued. googlevideo and tidal links are signed and die after a few
+// hours, so a long queue (or a restored session) used to fill up with
+// dead links. queued online tracks only carry their identity (video id,
+// title, artists) and an empty url; what they resolved to is cached
+// here by video id together with when it expires.
+// a source that won't play is crossed off and the next one in the chain
+// (local copy, lossless, yt-dlp, native) is tried, see failover.rs
+// -------------------------------------------------------------------
+
+// tidal links and manifests don't say when they expire
+const UNKNOWN_LIFETIME: Duration = Duration::from_secs(30 * 60);
+// a url has to outlive the track by this much to be handed out
+const SAFETY_MARGIN: Duration = Duration::from_secs(60);
+// a failed background lookup is not tried again before this
+const FAILED_BACKOFF: Duration = Duration::from_secs(30);
+// one transparent retry per song within this window
+const RETRY_WINDOW: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Source {
+    Local,
+    Lossless,
+    YtDlp,
+    Native,
+}
+
+// the order sources are fallen back to when one won't play
+const CHAIN: [Source; 4] = [Source::Local, Source::Lossless, Source::YtDlp, Source::Native];
+
+impl Source {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Source::Local => "local copy",
+            Source::Lossless => "lossless",
+            Source::YtDlp => "yt-dlp",
+            Source::Native => "youtube",
+        }
+    }
+}
+
+struct Resolved {
+    url: String,
+    expires: SystemTime,
+    info: Option<StreamInfo>,
+    source: Source,
+}
+
+static YT: OnceLock<api::YTMusic> = OnceLock::new();
+static CACHE: Mutex<BTreeMap<String, Resolved>> = Mutex::new(BTreeMap::new());
+// background lookups in flight (or failed), by video id
+static PENDING: Mutex<BTreeMap<String, Instant>> = Mutex::new(BTreeMap::new());
+static LAST_RETRY: Mutex<Option<(String, Instant)>> = Mutex::new(None);
+// sources that failed to play, by video id, until the track plays through
+static TRIED: Mutex<BTreeMap<String, Vec<Source>>> = Mutex::new(BTreeMap::new());
+
+// called once on startup
+pub fn init(yt: api::YTMusic) {
+    let _ = YT.set(yt);
+}
+
+fn is_remote_url(url: &str) -> bool {
+    url.starts_with("http") || url.ends_with(".mpd")
+}
+
+// streamed rather than read from disk: not resolved yet, or a signed url
+// from an old session
+pub fn is_remote(track: &Track) -> bool {
+    (track.url.is_empty() && track.video_id.is_some()) || is_remote_url(&track.url)
+}
+
+// googlevideo puts a unix timestamp in `expire=` (or `/expire/` in the path)
+fn expiry_of(url: &str) -> SystemTime {
+    let from_query = url
+        .split_once('?')
+        .and_then(|(_, q)| q.split('&').find_map(|kv| kv.strip_prefix("expire=")));
+    let from_path = || {
+        url.split_once("/expire/")
+            .and_then(|(_, rest)| rest.split('/').next())
+    };
+    from_query
+        .or_else(from_path)
+        .and_then(|s| s.parse::<u64>().ok())
+        .map(|secs| UNIX_EPOCH + Duration::from_secs(secs))
+        .unwrap_or_else(|| SystemTime::now() + UNKNOWN_LIFETIME)
+}
+
+// a finished download of this track in the music dir
+fn local_copy(track: &Track) -> Option<String> {
+    let dir = player::music_dir_path()?;
+    let name =
+        player::the_naming_format_in_which_i_have_saved_the_track_locally(&track.title, &track.artists);
+    ["flac", "opus"]
+        .iter()
+        .map(|ext| dir.join(format!("{}.{}", name, ext)))
+        .find(|p| p.exists())
+        .map(|p| p.to_string_lossy().to_string())
+}
+
+fn lookup(track: &Track, valid_for: Duration) -> Option<String> {
+    if !is_remote(track) {
+        return Some(track.url.clone());
+    }
+    let Some(id) = &track.video_id else {
+        // a bare url with nothing to look it up again by, take it as it is
+        return Some(track.url.clone());
+    };
+    if !was_tried(id, Source::Local)
+        && let Some(path) = local_copy(track)
+    {
+        return Some(path);
+    }
+    let cache = CACHE.lock().unwrap();
+    let entry = cache.get(id)?;
+    (entry.expires > SystemTime::now() + valid_for).then(|| entry.url.clone())
+}
+
+fn was_tried(id: &str, source: Source) -> bool {
+    TRIED
+        .lock()
+        .unwrap()
+        .get(id)
+        .is_some_and(|t| t.contains(&source))
+}
+
+// a source that will last through the whole track, None if it needs resolving
+pub fn peek(track: &Track) -> Option<String> {
+    let length = Duration::from_secs_f64(duration_to_seconds(&track.duration).max(0.0));
+    lookup(track, length + SAFETY_MARGIN)
+}
+
+// whatever the track was last resolved to, as long as it hasn't expired
+pub fn last_source(track: &Track) -> Option<String> {
+    lookup(track, Duration::ZERO)
+}
+
+// true when the backend's `source` is what `track` resolved to
+pub fn is_source_of(track: &Track, source: &str) -> bool {
+    last_source(track).as_deref() == Some(source)
+}
+
+// what `source` is (codec, bitrate, sample rate), as far as it can be told
+pub fn info_of(source: &str) -> Option<StreamInfo> {
+    if !is_remote_url(source) {
+        return StreamInfo::from_file(source);
+    }
+    let cache = CACHE.lock().unwrap();
+    cache
+        .values()
+        .find(|r| r.url == source)
+        .and_then(|r| r.info.clone())
+        .or_else(|| StreamInfo::from_url(source))
+}
+
+// which link of the chain `url` came from
+fn source_of(url: &str) -> Source {
+    if !is_remote_url(url) {
+        return Source::Local;
+    }
+    let cache = CACHE.lock().unwrap();
+    match cache.values().find(|r| r.url == url) {
+        Some(r) => r.source,
+        None if history::source_of(url) == PlaySource::Lossless => Source::Lossless,
+        // an unknown youtube link, most likely one the native resolver found
+        None => Source::Native,
+    }
+}
+
+// the user agent `url` has to be fetched with, None for the configured one.
+// the native resolver's urls are handed out to the android vr client only
+pub fn user_agent_for(url: &str) -> Option<&'static str> {
+    let cache = CACHE.lock().unwrap();
+    let native = cache.values().any(|r| r.url == url && r.source == Source::Native);
+    native.then_some(api::PLAYER_USER_AGENT)
+}
+
+// quality settings changed, whatever was resolved before is the wrong format now
+pub fn forget_all() {
+    CACHE.lock().unwrap().clear();
+    PENDING.lock().unwrap().clear();
+}
+
+// the url (or path) to hand the player for `track`. `verbose` says on the
+// status line what is being tried, for when the user is waiting on it
+pub async fn stream_url(
+    track: &Track,
+    verbose: bool,
+) -> Result<String, Box<dyn Error + Send + Sync>> {
+    if let Some(source) = peek(track) {
+        return Ok(source);
+    }
+    let id = track.video_id.clone().ok_or("Nothing to play")?;
+    let say = |msg: &str| {
+        if verbose && !config().game_mode {
+            set_status_line(Some(msg.to_string()));
+        }
+    };
+
+    let mut found = None;
+    if wants_lossless() && !was_tried(&id, Source::Lossless) {
+        say("Trying to fetch lossless");
+        found = fetch_lossless(track).await.map(|url| (url, None, Source::Lossless));
+    }
+    let (url, format, source) = match found {
+        Some(found) => found,
+        None => {
+            say("Fetching from youtube");
+            let yt = YT.get().ok_or("Not connected")?;
+            let (url, format) = yt.fetch_stream(&id).await.map_err(|e| e.to_string())?;
+            let source = if format.is_some() { Source::Native } else { Source::YtDlp };
+            (url, format, source)
+        }
+    };
+    remember(&id, &url, format, source);
+    PENDING.lock().unwrap().remove(&id);
+    Ok(url)
+}
+
+fn wants_lossless() -> bool {
+    config().lossless_mode && !quality::data_saver()
+}
+
+async fn fetch_lossless(track: &Track) -> Option<String> {
+    let clean_title =
+        crate::if_title_contains_non_english_and_other_language_script_return_only_english_part(
+            &track.title,
+        );
+    let query = format!("{} {}", clean_title, track.artists.join(" "));
+    fetch_flac_stream_url(&query, &track.duration).await.ok()
+}
+
+fn remember(id: &str, url: &str, format: Option<AudioFormat>, source: Source) {
+    let mut cache = CACHE.lock().unwrap();
+    let now = SystemTime::now();
+    cache.retain(|_, r| r.expires > now);
+    cache.insert(
+        id.to_string(),
+        Resolved {
+            url: url.to_string(),
+            expires: expiry_of(url),
+            info: format
+                .as_ref()
+                .map(StreamInfo::from_format)
+                .or_else(|| StreamInfo::from_url(url)),
+            source,
+        },
+    );
+}
+
+// `failed` (what `track` was playing from) won't play: crosses its source
+// off and resolves the next one in the chain that works. Err once every
+// source has been tried
+pub async fn next_source(
+    track: &Track,
+    failed: &str,
+) -> Result<(String, Source), Box<dyn Error + Send + Sync>> {
+    let id = track
+        .video_id
+        .clone()
+        .ok_or("nothing else to play it from")?;
+    let failed = source_of(failed);
+    CACHE.lock().unwrap().remove(&id);
+    let resolver = config().youtube.resolver;
+
+    for source in [failed].into_iter().chain(CHAIN) {
+        {
+            let mut tried = TRIED.lock().unwrap();
+            let tried = tried.entry(id.clone()).or_default();
+            if tried.contains(&source) {
+                continue;
+            }
+            tried.push(source);
+        }
+        if source == failed {
+            continue;
+        }
+        let found = match source {
+            Source::Local => local_copy(track).map(|path| (path, None)),
+            Source::Lossless if wants_lossless() => fetch_lossless(track).await.map(|url| (url, None)),
+            Source::YtDlp if resolver != StreamResolver::Native => {
+                let yt = YT.get().ok_or("Not connected")?;
+                yt.fetch_stream_url_ytdlp(&id)
+                    .await
+                    .map_err(|e| e.to_string())
+                    .ok()
+                    .map(|url| (url, None))
+            }
+            Source::Native if resolver != StreamResolver::YtDlp => {
+                let yt = YT.get().ok_or("Not connected")?;
+                yt.fetch_stream_native(&id).await.map_err(|e| e.to_string()).ok()
+            }
+            _ => None,
+        };
+        if let Some((url, format)) = found {
+            if source != Source::Local {
+                remember(&id, &url, format, source);
+            }
+            return Ok((url, source));
+        }
+    }
+    Err("no source left to try".into())
+}
+
+// `track` played through (or was given up on), start the chain over next time
+pub fn reset_chain(track: &Track) {
+    if let Some(id) = &track.video_id {
+        TRIED.lock().unwrap().remove(id);
+    }
+}
+
+// resolves `track` in the background so it is ready (and can be preloaded)
+// before it comes up
+pub fn prefetch(track: &Track) {
+    let Some(id) = &track.video_id else {
+        return;
+    };
+    if config().offline_mode || YT.get().is_none() {
+        return;
+    }
+    {
+        let mut pending = PENDING.lock().unwrap();
+        if pending
+            .get(id)
+            .is_some_and(|at| at.elapsed() < FAILED_BACKOFF)
+        {
+            return;
+        }
+        pending.insert(id.clone(), Instant::now());
+    }
+    let track = track.clone();
+    tokio::spawn(async move {
+        let _ = stream_url(&track, false).await;
+    });
+}
+
+// a refused or cut off stream most likely ran into an expired url. true
+// (once per song and minute) when it is worth resolving the track again
+// from the same source and carrying on where it stopped
+pub fn retry_allowed(track: &Track) -> bool {
+    if !is_remote(track) || config().offline_mode {
+        return false;
+    }
+    let Some(id) = &track.video_id else {
+        return false;
+    };
+
+    let mut last = LAST_RETRY.lock().unwrap();
+    if let Some((last_id, at)) = last.as_ref()
+        && last_id == id
+        && at.elapsed() < RETRY_WINDOW
+    {
+        return false;
+    }
+    *last = Some((id.clone(), Instant::now()));
+    CACHE.lock().unwrap().remove(id);
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_expiry_from_query_or_path() {
+        let at = |secs| UNIX_EPOCH + Duration::from_secs(secs);
+        assert_eq!(
+            expiry_of("https://rr3.googlevideo.com/videoplayback?itag=251&expire=1760000000&ei=x"),
+            at(1760000000)
+        );
+        assert_eq!(
+            expiry_of("https://rr3.googlevideo.com/videoplayback/expire/1760000123/ei/x/itag/251"),
+            at(1760000123)
+        );
+        // a query key that merely ends in "expire" doesn't count
+        assert_eq!(
+            expiry_of("https://x.test/a?noexpire=5&expire=1760000000"),
+            at(1760000000)
+        );
+    }
+
+    #[test]
+    fn unknown_expiry_gets_the_default_lifetime() {
+        for url in [
+            "https://x.test/audio.opus",
+            "https://x.test/a?expire=soon",
+            "/music/local.opus",
+        ] {
+            let left = expiry_of(url).duration_since(SystemTime::now()).unwrap();
+            assert!(left <= UNKNOWN_LIFETIME && left > UNKNOWN_LIFETIME - Duration::from_secs(5));
+        }
+    }
+
+    #[test]
+    fn tells_streams_from_files() {
+        assert!(is_remote(&Track::sample(Some("abc"), "")));
+        assert!(is_remote(&Track::sample(Some("abc"), "https://x.test/a")));
+        assert!(is_remote(&Track::sample(None, "/tmp/manifest.mpd")));
+        assert!(!is_remote(&Track::sample(Some("abc"), "/music/a.opus")));
+        assert!(!is_remote(&Track::sample(None, "")));
+    }
+
+    #[test]
+    fn only_native_urls_get_the_player_user_agent() {
+        let native = "https://rr1.googlevideo.com/videoplayback?c=ANDROID_VR&ua-test=1";
+        let ytdlp = "https://rr1.googlevideo.com/videoplayback?c=WEB&ua-test=2";
+        remember("ua-test-native", native, None, Source::Native);
+        remember("ua-test-ytdlp", ytdlp, None, Source::YtDlp);
+        assert_eq!(user_agent_for(native), Some(api::PLAYER_USER_AGENT));
+        assert_eq!(user_agent_for(ytdlp), None);
+        assert_eq!(user_agent_for("/music/a.opus"), None);
+    }
+}
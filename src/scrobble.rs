@@ -0,0 +1,494 @@
+use crate::history::HistoryEntry;
+use crate::{Track, config};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+// -------------------------------------------------------------------
+// listenbrainz + last.fm, both optional (enabled once their tokens are set)
+// every request runs on its own thread with the blocking client so a slow
+// api never stalls the player. a listen is written to scrobble_queue.jsonl
+// before it is sent and taken out once a service has it, so quitting mid
+// request (or failing) leaves it there for the next launch
+// -------------------------------------------------------------------
+
+// a pending scrobble is dropped after this many failed retries
+const MAX_ATTEMPTS: u32 = 20;
+
+// guards scrobble_queue.jsonl, never held across a request
+static QUEUE_LOCK: Mutex<()> = Mutex::new(());
+// queued listens a scrobble thread is sending right now, retry_queue leaves them be
+static IN_FLIGHT: Mutex<Vec<String>> = Mutex::new(Vec::new());
+// one retry_queue at a time, a second one would send everything twice
+static RETRYING: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Service {
+    ListenBrainz,
+    LastFm,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Listen {
+    artist: String,
+    title: String,
+    album: String,
+    duration_secs: Option<u64>,
+    video_id: Option<String>,
+    timestamp: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Pending {
+    service: Service,
+    listen: Listen,
+    attempts: u32,
+}
+
+impl Pending {
+    // the same listen for the same service
+    fn key(&self) -> String {
+        format!(
+            "{:?}\u{0}{}\u{0}{}\u{0}{}",
+            self.service, self.listen.timestamp, self.listen.artist, self.listen.title
+        )
+    }
+}
+
+enum SubmitError {
+    // network trouble, rate limit, bad token... worth trying again later
+    Retry,
+    // the service rejected the listen itself, retrying won't help
+    Drop,
+}
+
+fn queue_path() -> Option<PathBuf> {
+    Some(crate::settings::config_dir()?.join("scrobble_queue.jsonl"))
+}
+
+fn enabled_services() -> Vec<Service> {
+    let s = &config().scrobble;
+    let mut out = Vec::new();
+    if !s.listenbrainz.token.is_empty() {
+        out.push(Service::ListenBrainz);
+    }
+    if !s.lastfm.api_key.is_empty()
+        && !s.lastfm.api_secret.is_empty()
+        && !s.lastfm.session_key.is_empty()
+    {
+        out.push(Service::LastFm);
+    }
+    out
+}
+
+fn listen_from(title: &str, artists: &[String], album: &str, duration: &str) -> Listen {
+    let secs = crate::ui_common::duration_to_seconds(duration) as u64;
+    Listen {
+        artist: artists.join(", "),
+        title: title.to_string(),
+        album: album.to_string(),
+        duration_secs: (secs > 0).then_some(secs),
+        video_id: None,
+        timestamp: 0,
+    }
+}
+
+// last.fm rules, listenbrainz asks for the same: longer than 30s and
+// played for half its length or 4 minutes, whichever comes first
+fn passes_threshold(listen: &Listen, seconds_listened: f64) -> bool {
+    match listen.duration_secs {
+        Some(d) if d <= 30 => false,
+        Some(d) => seconds_listened >= (d as f64 / 2.0).min(240.0),
+        None => seconds_listened >= 240.0,
+    }
+}
+
+// ---- ENTRY POINTS ----
+
+pub fn now_playing(track: &Track) {
+    let services = enabled_services();
+    if services.is_empty() || track.artists.is_empty() || track.title.is_empty() {
+        return;
+    }
+    let mut listen = listen_from(&track.title, &track.artists, &track.album, &track.duration);
+    listen.video_id = track.video_id.clone();
+
+    std::thread::spawn(move || {
+        for service in services {
+            // now playing is best effort, it is stale by the time a retry would run
+            let _ = send(service, &listen, true);
+        }
+    });
+}
+
+pub fn scrobble(entry: &HistoryEntry) {
+    let services = enabled_services();
+    if services.is_empty() || entry.artists.is_empty() {
+        return;
+    }
+    let mut listen = listen_from(&entry.title, &entry.artists, &entry.album, &entry.duration);
+    listen.video_id = entry.video_id.clone();
+    listen.timestamp = entry.timestamp;
+
+    if !passes_threshold(&listen, entry.seconds_listened) {
+        return;
+    }
+
+    let pending: Vec<Pending> = services
+        .into_iter()
+        .map(|service| Pending {
+            service,
+            listen: listen.clone(),
+            attempts: 0,
+        })
+        .collect();
+    // on disk first: the app may quit (and exit) before the request is done
+    for p in &pending {
+        IN_FLIGHT.lock().unwrap().push(p.key());
+        enqueue(p);
+    }
+
+    std::thread::spawn(move || {
+        let mut sent_any = false;
+        for p in pending {
+            match send(p.service, &p.listen, false) {
+                Ok(()) => {
+                    sent_any = true;
+                    dequeue(&p.key());
+                }
+                Err(SubmitError::Drop) => dequeue(&p.key()),
+                // stays queued
+                Err(SubmitError::Retry) => {}
+            }
+            IN_FLIGHT.lock().unwrap().retain(|k| *k != p.key());
+        }
+        // the service is reachable again, good moment to catch up
+        if sent_any {
+            retry_queue();
+        }
+    });
+}
+
+// called once on startup
+pub fn flush_queue() {
+    if enabled_services().is_empty() {
+        return;
+    }
+    std::thread::spawn(retry_queue);
+}
+
+// ---- RETRY QUEUE ----
+
+// callers hold QUEUE_LOCK
+fn read_queue(path: &PathBuf) -> Vec<Pending> {
+    fs::read_to_string(path)
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|l| serde_json::from_str::<Pending>(l).ok())
+        .collect()
+}
+
+fn write_queue(path: &PathBuf, queue: &[Pending]) {
+    if queue.is_empty() {
+        let _ = fs::remove_file(path);
+    } else {
+        let out: String = queue
+            .iter()
+            .filter_map(|p| serde_json::to_string(p).ok())
+            .map(|l| l + "\n")
+            .collect();
+        let _ = fs::write(path, out);
+    }
+}
+
+fn enqueue(pending: &Pending) {
+    let _guard = QUEUE_LOCK.lock().unwrap();
+    let Some(path) = queue_path() else {
+        return;
+    };
+    let mut queue = read_queue(&path);
+    if !queue.iter().any(|p| p.key() == pending.key()) {
+        queue.push(Pending {
+            service: pending.service,
+            listen: pending.listen.clone(),
+            attempts: pending.attempts,
+        });
+    }
+    write_queue(&path, &queue);
+}
+
+fn dequeue(key: &str) {
+    let _guard = QUEUE_LOCK.lock().unwrap();
+    let Some(path) = queue_path() else {
+        return;
+    };
+    let mut queue = read_queue(&path);
+    queue.retain(|p| p.key() != key);
+    write_queue(&path, &queue);
+}
+
+fn retry_queue() {
+    if RETRYING.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    let Some(path) = queue_path() else {
+        RETRYING.store(false, Ordering::SeqCst);
+        return;
+    };
+    // a snapshot, the lock isn't held while sending
+    let pending = {
+        let _guard = QUEUE_LOCK.lock().unwrap();
+        read_queue(&path)
+    };
+    let in_flight = IN_FLIGHT.lock().unwrap().clone();
+
+    let enabled = enabled_services();
+    let mut done: Vec<String> = Vec::new();
+    let mut failed: Vec<String> = Vec::new();
+    let mut offline = Vec::new();
+
+    for p in pending {
+        // service switched off in config or already failing this round: keep for later
+        if !enabled.contains(&p.service)
+            || offline.contains(&p.service)
+            || in_flight.contains(&p.key())
+        {
+            continue;
+        }
+        match send(p.service, &p.listen, false) {
+            Ok(()) | Err(SubmitError::Drop) => done.push(p.key()),
+            Err(SubmitError::Retry) => {
+                offline.push(p.service);
+                failed.push(p.key());
+            }
+        }
+    }
+
+    // applied to the file as it is now, listens may have been queued meanwhile
+    let _guard = QUEUE_LOCK.lock().unwrap();
+    let mut queue = read_queue(&path);
+    queue.retain_mut(|p| {
+        let key = p.key();
+        if failed.contains(&key) {
+            p.attempts += 1;
+        }
+        !done.contains(&key) && p.attempts < MAX_ATTEMPTS
+    });
+    write_queue(&path, &queue);
+    RETRYING.store(false, Ordering::SeqCst);
+}
+
+// ---- HTTP ----
+
+fn client() -> Result<reqwest::blocking::Client, SubmitError> {
+    crate::net::blocking_client_builder()
+        .timeout(Duration::from_secs(10))
+        .user_agent(format!("whytui/{}", env!("CARGO_PKG_VERSION")))
+        .build()
+        .map_err(|_| SubmitError::Retry)
+}
+
+fn send(service: Service, listen: &Listen, now_playing: bool) -> Result<(), SubmitError> {
+    match service {
+        Service::ListenBrainz => send_listenbrainz(listen, now_playing),
+        Service::LastFm => send_lastfm(listen, now_playing),
+    }
+}
+
+fn classify_status(status: reqwest::StatusCode) -> Result<(), SubmitError> {
+    if status.is_success() {
+        Ok(())
+    } else if status.is_server_error()
+        || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+        || status == reqwest::StatusCode::UNAUTHORIZED
+        || status == reqwest::StatusCode::FORBIDDEN
+    {
+        Err(SubmitError::Retry)
+    } else {
+        Err(SubmitError::Drop)
+    }
+}
+
+fn send_listenbrainz(listen: &Listen, now_playing: bool) -> Result<(), SubmitError> {
+    let cfg = &config().scrobble.listenbrainz;
+
+    let mut additional_info = json!({
+        "submission_client": "whytui",
+        "submission_client_version": env!("CARGO_PKG_VERSION"),
+        "music_service": "music.youtube.com",
+    });
+    if let Some(d) = listen.duration_secs {
+        additional_info["duration_ms"] = json!(d * 1000);
+    }
+    if let Some(id) = &listen.video_id {
+        additional_info["origin_url"] = json!(format!("https://music.youtube.com/watch?v={}", id));
+    }
+
+    let mut metadata = json!({
+        "artist_name": listen.artist,
+        "track_name": listen.title,
+        "additional_info": additional_info,
+    });
+    if !listen.album.is_empty() {
+        metadata["release_name"] = json!(listen.album);
+    }
+
+    let mut item = json!({ "track_metadata": metadata });
+    if !now_playing {
+        item["listened_at"] = json!(listen.timestamp);
+    }
+    let body = json!({
+        "listen_type": if now_playing { "playing_now" } else { "single" },
+        "payload": [item],
+    });
+
+    let resp = client()?
+        .post(format!(
+            "{}/1/submit-listens",
+            cfg.api_url.trim_end_matches('/')
+        ))
+        .header("Authorization", format!("Token {}", cfg.token))
+        .json(&body)
+        .send()
+        .map_err(|_| SubmitError::Retry)?;
+
+    classify_status(resp.status())
+}
+
+// api_sig = md5 of every param (sorted, name+value, no format) + secret
+fn lastfm_signature(params: &BTreeMap<&str, String>, secret: &str) -> String {
+    let mut raw = String::new();
+    for (k, v) in params {
+        raw.push_str(k);
+        raw.push_str(v);
+    }
+    raw.push_str(secret);
+    format!("{:x}", md5::compute(raw.as_bytes()))
+}
+
+fn send_lastfm(listen: &Listen, now_playing: bool) -> Result<(), SubmitError> {
+    let cfg = &config().scrobble.lastfm;
+
+    let mut params: BTreeMap<&str, String> = BTreeMap::new();
+    params.insert(
+        "method",
+        if now_playing {
+            "track.updateNowPlaying"
+        } else {
+            "track.scrobble"
+        }
+        .to_string(),
+    );
+    params.insert("artist", listen.artist.clone());
+    params.insert("track", listen.title.clone());
+    if !listen.album.is_empty() {
+        params.insert("album", listen.album.clone());
+    }
+    if let Some(d) = listen.duration_secs {
+        params.insert("duration", d.to_string());
+    }
+    if !now_playing {
+        params.insert("timestamp", listen.timestamp.to_string());
+    }
+    params.insert("api_key", cfg.api_key.clone());
+    params.insert("sk", cfg.session_key.clone());
+
+    let sig = lastfm_signature(&params, &cfg.api_secret);
+    params.insert("api_sig", sig);
+    params.insert("format", "json".to_string());
+
+    let resp = client()?
+        .post(&cfg.api_url)
+        .form(&params)
+        .send()
+        .map_err(|_| SubmitError::Retry)?;
+
+    let status = resp.status();
+    let text = resp.text().unwrap_or_default();
+
+    // last.fm reports most problems as {"error": N} in the body
+    if let Ok(v) = serde_json::from_str::<Value>(&text)
+        && let Some(code) = v["error"].as_i64()
+    {
+        // 9 bad session, 11/16 service down, 26 suspended key, 29 rate limit
+        return match code {
+            9 | 11 | 16 | 26 | 29 => Err(SubmitError::Retry),
+            _ => Err(SubmitError::Drop),
+        };
+    }
+    classify_status(status)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn listen(duration: &str) -> Listen {
+        listen_from("Song", &["A".to_string(), "B".to_string()], "", duration)
+    }
+
+    #[test]
+    fn builds_listen_from_track_fields() {
+        let l = listen("3:20");
+        assert_eq!(l.artist, "A, B");
+        assert_eq!(l.duration_secs, Some(200));
+        assert_eq!(listen("").duration_secs, None);
+    }
+
+    #[test]
+    fn threshold_is_half_or_four_minutes() {
+        assert!(!passes_threshold(&listen("0:30"), 30.0));
+        assert!(passes_threshold(&listen("3:20"), 100.0));
+        assert!(!passes_threshold(&listen("3:20"), 99.0));
+        // a 20 minute mix counts after 4
+        assert!(passes_threshold(&listen("20:00"), 240.0));
+        assert!(!passes_threshold(&listen(""), 239.0));
+        assert!(passes_threshold(&listen(""), 240.0));
+    }
+
+    #[test]
+    fn queue_key_tells_services_apart() {
+        let pending = |service| Pending {
+            service,
+            listen: listen("3:00"),
+            attempts: 0,
+        };
+        let lb = pending(Service::ListenBrainz);
+        assert_eq!(lb.key(), pending(Service::ListenBrainz).key());
+        assert_ne!(lb.key(), pending(Service::LastFm).key());
+        let mut later = pending(Service::ListenBrainz);
+        later.listen.timestamp = 1;
+        assert_ne!(lb.key(), later.key());
+    }
+
+    #[test]
+    fn retries_only_what_may_work_later() {
+        use reqwest::StatusCode;
+        assert!(classify_status(StatusCode::OK).is_ok());
+        for code in [503, 429, 401, 403] {
+            let status = StatusCode::from_u16(code).unwrap();
+            assert!(matches!(classify_status(status), Err(SubmitError::Retry)));
+        }
+        assert!(matches!(
+            classify_status(StatusCode::BAD_REQUEST),
+            Err(SubmitError::Drop)
+        ));
+    }
+
+    #[test]
+    fn signs_sorted_params_with_secret() {
+        let mut params = BTreeMap::new();
+        params.insert("method", "track.scrobble".to_string());
+        params.insert("api_key", "K".to_string());
+        assert_eq!(
+            lastfm_signature(&params, "S"),
+            "597b0901cc8421d2a765c759cf0d9e51"
+        );
+    }
+}
@@ -0,0 +1,81 @@
+use crate::api::SongDetails;
+use crate::{Track, config, player};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::atomic::Ordering;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// -------------------------------------------------------------------
+// session.json in the config dir, written on quit and every few
+// seconds while playing so a crash loses at most one interval
+// -------------------------------------------------------------------
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Session {
+    pub current: Option<Track>,
+    pub position: f64,
+    pub queue: Vec<Track>,
+    pub recently_played: Vec<Track>,
+    pub related: Vec<SongDetails>,
+    pub playing_from_library: Option<(String, bool)>,
+    pub volume: i64,
+    pub repeat_mode: usize,
+    pub saved_at: u64,
+}
+
+fn session_path() -> Option<PathBuf> {
+    Some(crate::settings::config_dir()?.join("session.json"))
+}
+
+pub fn save(current: Option<&Track>) {
+    let Some(path) = session_path() else {
+        return;
+    };
+
+    let position = if current.is_some() {
+        player::get_time_info().map(|(pos, _)| pos).unwrap_or(0.0)
+    } else {
+        0.0
+    };
+
+    let session = Session {
+        current: current.cloned(),
+        position,
+        queue: crate::SONG_QUEUE.read().unwrap().clone(),
+        recently_played: crate::RECENTLY_PLAYED.read().unwrap().iter().cloned().collect(),
+        related: crate::RELATED_SONG_LIST.read().unwrap().clone(),
+        playing_from_library: crate::PLAYING_FROM_LIBRARY.read().unwrap().clone(),
+        volume: crate::VOLUME.load(Ordering::Relaxed),
+        repeat_mode: crate::REPEAT_MODE.load(Ordering::Relaxed),
+        saved_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+    };
+
+    // write + rename so a kill mid-write never leaves a half file behind
+    if let Ok(data) = serde_json::to_string(&session) {
+        let tmp = path.with_extension("json.tmp");
+        if std::fs::write(&tmp, data).is_ok() {
+            let _ = std::fs::rename(&tmp, &path);
+        }
+    }
+}
+
+pub fn load() -> Option<Session> {
+    let data = std::fs::read_to_string(session_path()?).ok()?;
+    serde_json::from_str(&data).ok()
+}
+
+// restores everything except the current track, which the caller starts with --start
+pub fn restore_state(session: &Session) {
+    *crate::SONG_QUEUE.write().unwrap() = session.queue.clone();
+    *crate::RECENTLY_PLAYED.write().unwrap() = session.recently_played.iter().cloned().collect();
+    *crate::RELATED_SONG_LIST.write().unwrap() = session.related.clone();
+    *crate::PLAYING_FROM_LIBRARY.write().unwrap() = session.playing_from_library.clone();
+    crate::VOLUME.store(
+        session.volume.clamp(0, config().playback.max_volume),
+        Ordering::Relaxed,
+    );
+    crate::REPEAT_MODE.store(session.repeat_mode % 3, Ordering::Relaxed);
+}
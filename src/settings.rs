@@ -0,0 +1,1019 @@
+use crate::cli::Flags;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// -------------------------------------------------------------------
+// config.toml lives next to cookies.txt in $MusicDir/whytui/config/
+// every field has a default so a partial (or empty) file is fine
+// -------------------------------------------------------------------
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AppConfig {
+    pub offline_mode: bool,
+    pub no_autoplay: bool,
+    pub lossless_mode: bool,
+    pub peak_lossless_mode: bool,
+    pub game_mode: bool,
+    pub download_mode: bool,
+    pub music_dir: Option<PathBuf>,
+    pub playback: PlaybackConfig,
+    pub autoplay: AutoplayConfig,
+    pub ui: UiConfig,
+    pub lyrics: LyricsConfig,
+    pub youtube: YoutubeConfig,
+    pub session: SessionConfig,
+    pub stats: StatsConfig,
+    pub scrobble: ScrobbleConfig,
+    pub remote: RemoteConfig,
+    pub effects: EffectsConfig,
+    pub loudness: LoudnessConfig,
+    pub timer: TimerConfig,
+    pub stream: StreamConfig,
+    pub network: NetworkConfig,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PlaybackConfig {
+    pub volume: i64,
+    pub max_volume: i64,
+    pub volume_step: i64,
+    pub seek_seconds: i64,
+    pub history_limit: usize,
+    // mpv, or null to run without audio (servers, ci)
+    pub backend: BackendKind,
+    // how far the null backend's clock moves each time it is polled (a quarter
+    // second times this), 10 = about ten seconds per second
+    pub null_speed: f64,
+    // overlap between songs, 0 = gapless (songs of one album always are)
+    pub crossfade_secs: f64,
+    // fade on pause, resume and skip, 0 = hard cut
+    pub fade_ms: u64,
+    // tracks at least this long count as mixes/podcasts and get their own speed
+    pub long_track_minutes: u64,
+    // mpv output device ("auto" = system default), `o` picks another one
+    pub audio_device: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AutoplayConfig {
+    pub related_fetch: usize,
+    pub batch_size: usize,
+    pub refill_below: usize,
+    pub offline_batch_size: usize,
+    pub offline_refill_below: usize,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UiConfig {
+    pub mode: usize,
+    pub search_results: usize,
+    pub library_page_size: usize,
+    pub status_timeout_ms: u64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LyricsConfig {
+    pub display_mode: LyricDisplayMode,
+    pub offset_step_ms: i64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct YoutubeConfig {
+    pub hl: String,
+    pub gl: String,
+    // empty = discover from the music.youtube.com ytcfg
+    pub client_version: String,
+    pub resolver: StreamResolver,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SessionConfig {
+    pub restore: RestoreMode,
+    pub save_interval_secs: u64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StatsConfig {
+    // week, month, year, all or <N>d
+    pub window: String,
+    pub top: usize,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScrobbleConfig {
+    pub listenbrainz: ListenBrainzConfig,
+    pub lastfm: LastfmConfig,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ListenBrainzConfig {
+    // empty = off
+    pub token: String,
+    pub api_url: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LastfmConfig {
+    // all three empty = off
+    pub api_key: String,
+    pub api_secret: String,
+    pub session_key: String,
+    pub api_url: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RemoteConfig {
+    // org.mpris.MediaPlayer2.whytui on the session bus (linux only)
+    pub mpris: bool,
+    // json control socket used by `whytui ctl` (unix only)
+    pub control_socket: bool,
+    // what a new tui / `whytui play` does when a tui is already running
+    pub already_running: AlreadyRunning,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EffectsConfig {
+    // used until another preset is picked with `e`, that choice is remembered
+    pub preset: String,
+    // what `e` cycles through, empty = every preset
+    pub cycle: Vec<String>,
+    // own presets, a built-in name here replaces the built-in one
+    pub presets: BTreeMap<String, EffectPreset>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EffectPreset {
+    // gain in dB at 31 62 125 250 500 1k 2k 4k 8k 16k hz, empty = no eq
+    pub bands: Vec<f64>,
+    // ffmpeg filters run after the eq, e.g. "acompressor=ratio=4"
+    pub filters: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LoudnessConfig {
+    // which replaygain tags mpv applies to local files
+    pub replaygain: ReplayGainMode,
+    // streams carry no tags, normalise them on the fly instead
+    pub stream: StreamNormalize,
+    // measure downloads (ebu r128) and tag them with replaygain
+    pub tag_downloads: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TimerConfig {
+    // sleep timer lengths the `z` key steps through, in minutes
+    pub presets: Vec<u64>,
+    // the sleep timer turns the volume down over its last seconds
+    pub fade_out_secs: u64,
+    // a wake-up alarm starts silent and gets to full volume over this
+    pub wake_ramp_secs: u64,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ReplayGainMode {
+    Track,
+    Off,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum StreamNormalize {
+    Loudnorm,
+    Dynaudnorm,
+    Off,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StreamConfig {
+    // best, 251 / 250 / 249 (opus, high to low) or 140 (aac)
+    pub format: String,
+    // kbps, 0 = no cap
+    pub max_kbps: u32,
+    pub data_saver: bool,
+    // the cap while data saver is on
+    pub data_saver_kbps: u32,
+    // [stream.profiles.<name>] in use, empty = the settings above
+    pub profile: String,
+    pub profiles: BTreeMap<String, StreamProfile>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NetworkConfig {
+    // netscape cookies.txt, empty = cookies.txt next to config.toml
+    pub cookies: PathBuf,
+    // http(s) proxy for everything, empty = none
+    pub proxy: String,
+    // empty = the web player's
+    pub user_agent: String,
+    // appended to every run of the tool
+    pub ytdlp_args: Vec<String>,
+    pub mpv_args: Vec<String>,
+    pub ffmpeg_args: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StreamProfile {
+    // unset = same as in [stream]
+    pub format: Option<String>,
+    pub max_kbps: Option<u32>,
+    pub data_saver: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum StreamResolver {
+    // native first, yt-dlp when that fails
+    Auto,
+    Native,
+    YtDlp,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum AlreadyRunning {
+    // start anyway, every instance has its own sockets and temp dir
+    Allow,
+    Refuse,
+    // hand the arguments to the running tui over its control socket
+    Forward,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum BackendKind {
+    Mpv,
+    Null,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum RestoreMode {
+    Ask,
+    Always,
+    Never,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LyricDisplayMode {
+    Original,
+    Romanized,
+    Translated,
+}
+
+impl LyricDisplayMode {
+    // same numbering as ui_common::LYRIC_DISPLAY_MODE
+    pub fn as_u8(self) -> u8 {
+        match self {
+            LyricDisplayMode::Original => 0,
+            LyricDisplayMode::Romanized => 1,
+            LyricDisplayMode::Translated => 2,
+        }
+    }
+}
+
+impl Default for PlaybackConfig {
+    fn default() -> Self {
+        Self {
+            volume: 75,
+            max_volume: 150,
+            volume_step: 5,
+            seek_seconds: 5,
+            history_limit: 50,
+            backend: BackendKind::Mpv,
+            null_speed: 1.0,
+            crossfade_secs: 0.0,
+            fade_ms: 0,
+            long_track_minutes: 20,
+            audio_device: "auto".to_string(),
+        }
+    }
+}
+
+impl Default for AutoplayConfig {
+    fn default() -> Self {
+        Self {
+            related_fetch: 50,
+            batch_size: 5,
+            refill_below: 2,
+            offline_batch_size: 5,
+            offline_refill_below: 3,
+        }
+    }
+}
+
+impl Default for UiConfig {
+    fn default() -> Self {
+        Self {
+            mode: 1,
+            search_results: 5,
+            library_page_size: 5,
+            status_timeout_ms: 1000,
+        }
+    }
+}
+
+impl Default for LyricsConfig {
+    fn default() -> Self {
+        Self {
+            display_mode: LyricDisplayMode::Original,
+            offset_step_ms: 100,
+        }
+    }
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self {
+            restore: RestoreMode::Ask,
+            save_interval_secs: 15,
+        }
+    }
+}
+
+impl Default for StatsConfig {
+    fn default() -> Self {
+        Self {
+            window: "month".to_string(),
+            top: 10,
+        }
+    }
+}
+
+impl Default for ListenBrainzConfig {
+    fn default() -> Self {
+        Self {
+            token: String::new(),
+            api_url: "https://api.listenbrainz.org".to_string(),
+        }
+    }
+}
+
+impl Default for LastfmConfig {
+    fn default() -> Self {
+        Self {
+            api_key: String::new(),
+            api_secret: String::new(),
+            session_key: String::new(),
+            api_url: "https://ws.audioscrobbler.com/2.0/".to_string(),
+        }
+    }
+}
+
+impl Default for RemoteConfig {
+    fn default() -> Self {
+        Self {
+            mpris: true,
+            control_socket: true,
+            already_running: AlreadyRunning::Allow,
+        }
+    }
+}
+
+impl Default for EffectsConfig {
+    fn default() -> Self {
+        Self {
+            preset: "flat".to_string(),
+            cycle: Vec::new(),
+            presets: BTreeMap::new(),
+        }
+    }
+}
+
+impl Default for LoudnessConfig {
+    fn default() -> Self {
+        Self {
+            replaygain: ReplayGainMode::Track,
+            stream: StreamNormalize::Off,
+            tag_downloads: true,
+        }
+    }
+}
+
+impl Default for TimerConfig {
+    fn default() -> Self {
+        Self {
+            presets: vec![15, 30, 45, 60, 90],
+            fade_out_secs: 60,
+            wake_ramp_secs: 120,
+        }
+    }
+}
+
+impl Default for StreamConfig {
+    fn default() -> Self {
+        Self {
+            format: "best".to_string(),
+            max_kbps: 0,
+            data_saver: false,
+            data_saver_kbps: 64,
+            profile: String::new(),
+            profiles: BTreeMap::new(),
+        }
+    }
+}
+
+impl Default for YoutubeConfig {
+    fn default() -> Self {
+        let (hl, gl) = locale_from_env();
+        Self {
+            hl,
+            gl,
+            client_version: String::new(),
+            resolver: StreamResolver::Auto,
+        }
+    }
+}
+
+// LANG=de_AT.UTF-8 -> ("de", "AT"), falls back to the old en/IN
+fn locale_from_env() -> (String, String) {
+    let lang = ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|k| std::env::var(k).ok())
+        .find(|v| !v.is_empty() && v != "C" && v != "POSIX")
+        .unwrap_or_default();
+
+    let base = lang.split(['.', '@']).next().unwrap_or("");
+    let mut parts = base.split(['_', '-']);
+    let hl = parts.next().filter(|s| s.len() == 2).map(|s| s.to_lowercase());
+    let gl = parts.next().filter(|s| s.len() == 2).map(|s| s.to_uppercase());
+
+    (
+        hl.unwrap_or_else(|| "en".to_string()),
+        gl.unwrap_or_else(|| "IN".to_string()),
+    )
+}
+
+// written on first launch so users can see every knob
+const DEFAULT_CONFIG: &str = r#"# whytui configuration
+# command line flags (-o -d -n -l -pl -g) override the values below
+
+# offline_mode = false
+# download_mode = false
+# no_autoplay = false
+# lossless_mode = false
+# peak_lossless_mode = false
+# game_mode = false
+
+# where songs are cached (defaults to ~/Music/whytui)
+# music_dir = "/path/to/music"
+
+[playback]
+# volume = 75
+# max_volume = 150
+# volume_step = 5
+# seek_seconds = 5
+# history_limit = 50
+# "mpv" or "null" (plays nothing, for headless servers and ci; same as --backend null)
+# backend = "mpv"
+# null_speed = 1.0
+# seconds two songs overlap, 0 = gapless (songs of the same album stay gapless)
+# crossfade_secs = 0
+# short fade on pause, resume and skip, in milliseconds
+# fade_ms = 0
+# tracks this long (mixes, podcasts) remember their own playback speed
+# long_track_minutes = 20
+# output device as mpv names it (see `mpv --audio-device=help`), "auto" = system default.
+# when left at "auto", the last one picked with `o` is used
+# audio_device = "auto"
+
+[autoplay]
+# related_fetch = 50
+# batch_size = 5
+# refill_below = 2
+# offline_batch_size = 5
+# offline_refill_below = 3
+
+[ui]
+# 1, 2 or 3 (same as cycling with `v`)
+# mode = 1
+# search_results = 5
+# library_page_size = 5
+# status_timeout_ms = 1000
+
+[lyrics]
+# original, romanized or translated
+# display_mode = "original"
+# offset_step_ms = 100
+
+[youtube]
+# language and region for search, charts and recommendations
+# (default comes from $LANG, e.g. en_US.UTF-8 -> hl = "en", gl = "US")
+# hl = "en"
+# gl = "IN"
+# pin the WEB_REMIX client version instead of discovering it
+# client_version = "1.20251215.03.00"
+# how stream urls are looked up: "native" asks youtube's player endpoint
+# directly, "yt-dlp" runs yt-dlp, "auto" tries native and falls back to yt-dlp
+# resolver = "auto"
+
+[session]
+# resume the last track/queue on launch: ask, always or never
+# restore = "ask"
+# save_interval_secs = 15
+
+[stats]
+# default window for `whytui stats` and the `S` screen: week, month, year, all or e.g. 90d
+# window = "month"
+# top = 10
+
+# plays are scrobbled after half the track or 4 minutes, failed ones are
+# kept in config/scrobble_queue.jsonl and retried
+[scrobble.listenbrainz]
+# token from https://listenbrainz.org/settings/
+# token = ""
+# api_url = "https://api.listenbrainz.org"
+
+[scrobble.lastfm]
+# api key + secret from https://www.last.fm/api/account/create and a session key
+# from auth.getMobileSession / auth.getSession
+# api_key = ""
+# api_secret = ""
+# session_key = ""
+# api_url = "https://ws.audioscrobbler.com/2.0/"
+
+[remote]
+# media keys / playerctl / desktop widgets through MPRIS (linux only)
+# mpris = true
+# socket for `whytui ctl` scripts and global hotkeys, lives in
+# $XDG_RUNTIME_DIR/whytui/<pid>/ctl.sock
+# control_socket = true
+# when another whytui is already running: "allow" a second one, "refuse" to
+# start, or "forward" (`whytui play <id>` plays it in the running one)
+# already_running = "allow"
+
+[effects]
+# equalizer / effect preset, `e` cycles through them and the last pick is
+# remembered. built in: flat, bass, vocal, loudness, night
+# preset = "flat"
+# cycle = ["flat", "bass", "vocal", "loudness", "night"]
+#
+# [effects.presets.mine]
+# # dB at 31 62 125 250 500 1k 2k 4k 8k 16k hz
+# bands = [4, 3, 1, 0, 0, 0, 1, 2, 3, 3]
+# # any ffmpeg audio filters, applied after the eq
+# filters = "acompressor=ratio=3"
+
+[loudness]
+# replaygain tags of local files: "track" or "off"
+# replaygain = "track"
+# streams have no tags: "loudnorm", "dynaudnorm" or "off"
+# stream = "off"
+# measure downloaded songs (needs ffmpeg) and write replaygain tags
+# tag_downloads = true
+
+[timer]
+# `z` steps through these sleep timers (minutes), then "after this song", then off
+# presets = [15, 30, 45, 60, 90]
+# the sleep timer fades out over its last seconds
+# fade_out_secs = 60
+# wake-up alarms fade in from silence over this many seconds
+# wake_ramp_secs = 120
+
+[stream]
+# youtube audio to stream and download: "best", "251", "250", "249" (opus,
+# high to low) or "140" (aac)
+# format = "best"
+# never stream above this many kbps, 0 = no cap
+# max_kbps = 0
+# data saver: no lossless, at most data_saver_kbps and the next song isn't
+# fetched ahead of time
+# data_saver = false
+# data_saver_kbps = 64
+# profile in use (also `--profile <name>`, or type `profile=<name>`)
+# profile = ""
+#
+# [stream.profiles.mobile]
+# format = "249"
+# data_saver = true
+
+[network]
+# cookies exported from a logged-in browser (netscape format), used for
+# search and handed to yt-dlp and mpv. empty = cookies.txt next to
+# this file. needed for age-restricted and members-only songs
+# cookies = ""
+# http(s) proxy for every request and tool, e.g. "http://127.0.0.1:8080"
+# proxy = ""
+# empty = the same as the youtube web player
+# user_agent = ""
+# extra arguments for each tool, e.g. ["--force-ipv4"]
+# ytdlp_args = []
+# mpv_args = []
+# ffmpeg_args = []
+"#;
+
+pub fn config_dir() -> Option<PathBuf> {
+    let mut d = dirs::audio_dir()?;
+    d.push("whytui");
+    d.push("config");
+    Some(d)
+}
+
+pub fn load_config() -> Result<AppConfig, String> {
+    let dir = config_dir().ok_or("No audio dir")?;
+    fs::create_dir_all(&dir).map_err(|e| format!("Could not create {}: {}", dir.display(), e))?;
+
+    let path = dir.join("config.toml");
+    if !path.exists() {
+        let _ = fs::write(&path, DEFAULT_CONFIG);
+        return Ok(AppConfig::default());
+    }
+    load_config_from(&path)
+}
+
+pub fn load_config_from(path: &Path) -> Result<AppConfig, String> {
+    let raw = fs::read_to_string(path)
+        .map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+
+    let cfg: AppConfig =
+        toml::from_str(&raw).map_err(|e| format!("Invalid {}:\n{}", path.display(), e))?;
+
+    let problems = cfg.validate();
+    if !problems.is_empty() {
+        return Err(format!(
+            "Invalid {}:\n  - {}",
+            path.display(),
+            problems.join("\n  - ")
+        ));
+    }
+    Ok(cfg)
+}
+
+impl AppConfig {
+    // flags only ever switch things on, the file decides the rest
+    pub fn apply_cli_flags(&mut self, flags: &Flags) {
+        self.download_mode |= flags.download; //save songs to music_dir
+        self.offline_mode |= flags.offline; //play only from offline library
+        self.no_autoplay |= flags.nomix; //dont auto queue
+        self.peak_lossless_mode |= flags.peak_lossless; //try fetching peak from tidal
+        self.lossless_mode |= flags.lossless || self.peak_lossless_mode; //try fetching from tidal
+        self.game_mode |= flags.guess; //guess quality (to be used with --lossless)
+        if let Some(b) = flags.backend {
+            self.playback.backend = b; //audio backend (mpv or null)
+        }
+        if let Some(p) = &flags.profile {
+            self.stream.profile = p.clone(); //stream quality profile
+        }
+    }
+
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        let p = &self.playback;
+
+        if p.max_volume < 1 || p.max_volume > 1000 {
+            problems.push(format!(
+                "playback.max_volume must be between 1 and 1000 (got {})",
+                p.max_volume
+            ));
+        }
+        if p.volume < 0 || p.volume > p.max_volume {
+            problems.push(format!(
+                "playback.volume must be between 0 and playback.max_volume ({}) (got {})",
+                p.max_volume, p.volume
+            ));
+        }
+        if p.volume_step < 1 {
+            problems.push(format!(
+                "playback.volume_step must be at least 1 (got {})",
+                p.volume_step
+            ));
+        }
+        if p.seek_seconds < 1 {
+            problems.push(format!(
+                "playback.seek_seconds must be at least 1 (got {})",
+                p.seek_seconds
+            ));
+        }
+        if p.history_limit < 1 {
+            problems.push("playback.history_limit must be at least 1".to_string());
+        }
+        if !(p.null_speed > 0.0 && p.null_speed <= 1000.0) {
+            problems.push(format!(
+                "playback.null_speed must be between 0 and 1000 (got {})",
+                p.null_speed
+            ));
+        }
+        if !(0.0..=12.0).contains(&p.crossfade_secs) {
+            problems.push(format!(
+                "playback.crossfade_secs must be between 0 and 12 (got {})",
+                p.crossfade_secs
+            ));
+        }
+        if p.fade_ms > 5000 {
+            problems.push(format!(
+                "playback.fade_ms must be at most 5000 (got {})",
+                p.fade_ms
+            ));
+        }
+        if p.long_track_minutes < 1 {
+            problems.push("playback.long_track_minutes must be at least 1".to_string());
+        }
+        if p.audio_device.trim().is_empty() {
+            problems.push(
+                "playback.audio_device must not be empty (use \"auto\" for the default)"
+                    .to_string(),
+            );
+        }
+
+        if self.remote.already_running == AlreadyRunning::Forward && !self.remote.control_socket {
+            problems.push(
+                "remote.already_running = \"forward\" needs remote.control_socket = true"
+                    .to_string(),
+            );
+        }
+
+        let e = &self.effects;
+        for (name, preset) in &e.presets {
+            if !preset.bands.is_empty() && preset.bands.len() != crate::effects::BANDS.len() {
+                problems.push(format!(
+                    "effects.presets.{}.bands needs {} values (got {})",
+                    name,
+                    crate::effects::BANDS.len(),
+                    preset.bands.len()
+                ));
+            }
+            if preset.bands.iter().any(|g| !(-20.0..=20.0).contains(g)) {
+                problems.push(format!(
+                    "effects.presets.{}.bands must be between -20 and 20 dB",
+                    name
+                ));
+            }
+        }
+        for name in std::iter::once(&e.preset).chain(&e.cycle) {
+            if !e.presets.contains_key(name) && !crate::effects::is_builtin(name) {
+                problems.push(format!("unknown effects preset '{}'", name));
+            }
+        }
+
+        let t = &self.timer;
+        if t.presets.iter().any(|m| *m < 1 || *m > 24 * 60) {
+            problems.push("timer.presets must be between 1 and 1440 minutes".to_string());
+        }
+        if t.fade_out_secs > 3600 || t.wake_ramp_secs > 3600 {
+            problems.push(
+                "timer.fade_out_secs and timer.wake_ramp_secs must be at most 3600".to_string(),
+            );
+        }
+
+        let st = &self.stream;
+        let formats = std::iter::once(("stream.format".to_string(), Some(&st.format))).chain(
+            st.profiles
+                .iter()
+                .map(|(name, p)| (format!("stream.profiles.{}.format", name), p.format.as_ref())),
+        );
+        for (key, format) in formats {
+            if let Some(f) = format
+                && !crate::quality::is_format(f)
+            {
+                problems.push(format!(
+                    "{} must be one of {} (got \"{}\")",
+                    key,
+                    crate::quality::FORMATS.join(", "),
+                    f
+                ));
+            }
+        }
+        if st.data_saver_kbps < 32 {
+            problems.push(format!(
+                "stream.data_saver_kbps must be at least 32 (got {})",
+                st.data_saver_kbps
+            ));
+        }
+        if !st.profile.is_empty() && !st.profiles.contains_key(&st.profile) {
+            problems.push(format!(
+                "stream.profile \"{}\" has no [stream.profiles.{}]",
+                st.profile, st.profile
+            ));
+        }
+
+        let net = &self.network;
+        if !net.proxy.is_empty()
+            && !net.proxy.starts_with("http://")
+            && !net.proxy.starts_with("https://")
+        {
+            problems.push(format!(
+                "network.proxy must be an http(s) url (got \"{}\")",
+                net.proxy
+            ));
+        }
+        if !net.cookies.as_os_str().is_empty() && !net.cookies.is_file() {
+            problems.push(format!(
+                "network.cookies {} is not a file",
+                net.cookies.display()
+            ));
+        }
+
+        let a = &self.autoplay;
+        if a.batch_size < 1 || a.offline_batch_size < 1 {
+            problems.push(
+                "autoplay.batch_size and autoplay.offline_batch_size must be at least 1"
+                    .to_string(),
+            );
+        }
+        if a.related_fetch < a.batch_size {
+            problems.push(format!(
+                "autoplay.related_fetch ({}) must not be smaller than autoplay.batch_size ({})",
+                a.related_fetch, a.batch_size
+            ));
+        }
+
+        if !(1..=3).contains(&self.ui.mode) {
+            problems.push(format!("ui.mode must be 1, 2 or 3 (got {})", self.ui.mode));
+        }
+        if self.ui.search_results < 1 || self.ui.search_results > 9 {
+            // results are picked with a single digit key
+            problems.push(format!(
+                "ui.search_results must be between 1 and 9 (got {})",
+                self.ui.search_results
+            ));
+        }
+        if self.ui.library_page_size < 1 || self.ui.library_page_size > 9 {
+            problems.push(format!(
+                "ui.library_page_size must be between 1 and 9 (got {})",
+                self.ui.library_page_size
+            ));
+        }
+
+        if self.lyrics.offset_step_ms < 1 {
+            problems.push(format!(
+                "lyrics.offset_step_ms must be at least 1 (got {})",
+                self.lyrics.offset_step_ms
+            ));
+        }
+
+        let yt = &self.youtube;
+        if yt.hl.len() < 2 || !yt.hl.chars().all(|c| c.is_ascii_alphabetic() || c == '-') {
+            problems.push(format!(
+                "youtube.hl must be a language code like \"en\" (got \"{}\")",
+                yt.hl
+            ));
+        }
+        if yt.gl.len() != 2 || !yt.gl.chars().all(|c| c.is_ascii_uppercase()) {
+            problems.push(format!(
+                "youtube.gl must be a two letter upper case region like \"US\" (got \"{}\")",
+                yt.gl
+            ));
+        }
+        if !yt.client_version.is_empty()
+            && !yt.client_version.chars().all(|c| c.is_ascii_digit() || c == '.')
+        {
+            problems.push(format!(
+                "youtube.client_version must look like 1.20251215.03.00 (got \"{}\")",
+                yt.client_version
+            ));
+        }
+
+        if self.session.save_interval_secs < 1 {
+            problems.push("session.save_interval_secs must be at least 1".to_string());
+        }
+
+        if let Err(e) = crate::stats::parse_window(&self.stats.window) {
+            problems.push(format!("stats.window: {}", e));
+        }
+        if self.stats.top < 1 || self.stats.top > 100 {
+            problems.push(format!(
+                "stats.top must be between 1 and 100 (got {})",
+                self.stats.top
+            ));
+        }
+
+        let lb = &self.scrobble.listenbrainz;
+        let fm = &self.scrobble.lastfm;
+        for (key, url) in [
+            ("scrobble.listenbrainz.api_url", &lb.api_url),
+            ("scrobble.lastfm.api_url", &fm.api_url),
+        ] {
+            if !url.starts_with("http://") && !url.starts_with("https://") {
+                problems.push(format!("{} must be an http(s) url (got \"{}\")", key, url));
+            }
+        }
+        let fm_set = [&fm.api_key, &fm.api_secret, &fm.session_key]
+            .iter()
+            .filter(|v| !v.is_empty())
+            .count();
+        if fm_set != 0 && fm_set != 3 {
+            problems.push(
+                "scrobble.lastfm needs api_key, api_secret and session_key together".to_string(),
+            );
+        }
+
+        if let Some(dir) = &self.music_dir {
+            if dir.as_os_str().is_empty() {
+                problems
+                    .push("music_dir must not be empty (remove it to use the default)".to_string());
+            } else if dir.exists() && !dir.is_dir() {
+                problems.push(format!("music_dir {} is not a directory", dir.display()));
+            }
+        }
+
+        problems
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(raw: &str) -> AppConfig {
+        toml::from_str(raw).expect("config parses")
+    }
+
+    fn problems(raw: &str) -> Vec<String> {
+        parse(raw).validate()
+    }
+
+    #[test]
+    fn defaults_are_valid() {
+        assert!(AppConfig::default().validate().is_empty());
+        assert!(problems("").is_empty());
+    }
+
+    #[test]
+    fn commented_template_is_valid() {
+        assert!(problems(DEFAULT_CONFIG).is_empty());
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        assert!(toml::from_str::<AppConfig>("[playback]\nvolumee = 3").is_err());
+        assert!(toml::from_str::<AppConfig>("nope = true").is_err());
+    }
+
+    #[test]
+    fn out_of_range_values_name_their_key() {
+        let p = problems("[playback]\nvolume = 500\nmax_volume = 100");
+        assert_eq!(p.len(), 1);
+        assert!(p[0].starts_with("playback.volume"));
+
+        let p = problems("[ui]\nmode = 4");
+        assert!(p.iter().any(|p| p.starts_with("ui.mode")));
+    }
+
+    #[test]
+    fn lastfm_needs_all_three_keys() {
+        let p = problems("[scrobble.lastfm]\napi_key = \"k\"");
+        assert!(p.iter().any(|p| p.contains("scrobble.lastfm")));
+        let ok = "[scrobble.lastfm]\napi_key = \"k\"\napi_secret = \"s\"\nsession_key = \"x\"";
+        assert!(problems(ok).is_empty());
+    }
+
+    #[test]
+    fn stream_settings_are_checked() {
+        assert!(!problems("[stream]\nformat = \"999\"").is_empty());
+        assert!(!problems("[stream]\nprofile = \"car\"").is_empty());
+        assert!(!problems("[stream]\ndata_saver_kbps = 8").is_empty());
+        let ok = "[stream]\nprofile = \"car\"\n[stream.profiles.car]\nformat = \"249\"";
+        assert!(problems(ok).is_empty());
+    }
+
+    #[test]
+    fn network_proxy_must_be_http() {
+        assert!(!problems("[network]\nproxy = \"socks5://127.0.0.1:9050\"").is_empty());
+        assert!(problems("[network]\nproxy = \"http://127.0.0.1:8080\"").is_empty());
+        assert!(!problems("[network]\ncookies = \"/nonexistent/cookies.txt\"").is_empty());
+    }
+
+    #[test]
+    fn cli_flags_only_switch_things_on() {
+        let mut cfg = parse("download_mode = true");
+        let flags = Flags {
+            lossless: true,
+            profile: Some("car".to_string()),
+            ..Default::default()
+        };
+        cfg.apply_cli_flags(&flags);
+        assert!(cfg.download_mode);
+        assert!(cfg.lossless_mode);
+        assert!(!cfg.offline_mode);
+        assert_eq!(cfg.stream.profile, "car");
+
+        let mut cfg = parse("");
+        cfg.apply_cli_flags(&Flags {
+            peak_lossless: true,
+            ..Default::default()
+        });
+        assert!(cfg.lossless_mode);
+    }
+}
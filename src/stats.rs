@@ -0,0 +1,504 @@
+use crate::history::{HistoryEntry, PlaySource};
+use chrono::{Datelike, Local, TimeZone, Timelike};
+use colored::Colorize;
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// -------------------------------------------------------------------
+// everything here is computed from history.jsonl on demand,
+// nothing is cached or stored separately
+// -------------------------------------------------------------------
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Window {
+    Days(u64),
+    All,
+}
+
+// "week", "month", "year", "all" or "<N>d"
+pub fn parse_window(s: &str) -> Result<Window, String> {
+    match s.trim().to_lowercase().as_str() {
+        "week" => Ok(Window::Days(7)),
+        "month" => Ok(Window::Days(30)),
+        "year" => Ok(Window::Days(365)),
+        "all" => Ok(Window::All),
+        other => other
+            .strip_suffix('d')
+            .and_then(|n| n.parse::<u64>().ok())
+            .filter(|n| *n > 0)
+            .map(Window::Days)
+            .ok_or_else(|| {
+                format!(
+                    "unknown window '{}' (use week, month, year, all or e.g. 90d)",
+                    s
+                )
+            }),
+    }
+}
+
+impl Window {
+    pub fn label(self) -> String {
+        match self {
+            Window::Days(7) => "last 7 days".to_string(),
+            Window::Days(30) => "last 30 days".to_string(),
+            Window::Days(365) => "last 365 days".to_string(),
+            Window::Days(n) => format!("last {} days", n),
+            Window::All => "all time".to_string(),
+        }
+    }
+
+    pub fn range(self) -> (u64, u64) {
+        let now = now_secs();
+        match self {
+            Window::Days(n) => (now.saturating_sub(n.saturating_mul(86400)), u64::MAX),
+            Window::All => (0, u64::MAX),
+        }
+    }
+}
+
+// the windows the stats screen cycles through with n / p
+pub const SCREEN_WINDOWS: &[Window] = &[
+    Window::Days(7),
+    Window::Days(30),
+    Window::Days(365),
+    Window::All,
+];
+
+pub fn year_range(year: i32) -> Option<(u64, u64)> {
+    let start = Local.with_ymd_and_hms(year, 1, 1, 0, 0, 0).earliest()?;
+    let end = Local.with_ymd_and_hms(year.checked_add(1)?, 1, 1, 0, 0, 0).earliest()?;
+    Some((start.timestamp().max(0) as u64, end.timestamp().max(0) as u64))
+}
+
+pub fn current_year() -> i32 {
+    Local::now().year()
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Ranked {
+    pub label: String,
+    pub plays: usize,
+    pub seconds: f64,
+}
+
+#[derive(Debug, Default)]
+pub struct Stats {
+    pub plays: usize,
+    pub skipped: usize,
+    pub seconds: f64,
+    pub lossless: usize,
+    pub from_cache: usize,
+    pub top_tracks: Vec<Ranked>,
+    pub top_artists: Vec<Ranked>,
+    pub top_albums: Vec<Ranked>,
+    // seconds listened per local hour of day
+    pub by_hour: [f64; 24],
+}
+
+impl Stats {
+    pub fn skip_rate(&self) -> f64 {
+        if self.plays == 0 {
+            0.0
+        } else {
+            self.skipped as f64 / self.plays as f64 * 100.0
+        }
+    }
+
+    pub fn lossless_rate(&self) -> f64 {
+        if self.plays == 0 {
+            0.0
+        } else {
+            self.lossless as f64 / self.plays as f64 * 100.0
+        }
+    }
+}
+
+pub fn compute(entries: &[HistoryEntry], range: (u64, u64), top: usize) -> Stats {
+    let mut stats = Stats::default();
+    let mut tracks: HashMap<String, Ranked> = HashMap::new();
+    let mut artists: HashMap<String, Ranked> = HashMap::new();
+    let mut albums: HashMap<String, Ranked> = HashMap::new();
+
+    for e in entries
+        .iter()
+        .filter(|e| e.timestamp >= range.0 && e.timestamp < range.1)
+    {
+        stats.plays += 1;
+        stats.seconds += e.seconds_listened;
+        if e.skipped {
+            stats.skipped += 1;
+        }
+        if e.lossless || e.source == PlaySource::Lossless {
+            stats.lossless += 1;
+        }
+        if e.source == PlaySource::Cache {
+            stats.from_cache += 1;
+        }
+        if let Some(t) = Local.timestamp_opt(e.timestamp as i64, 0).single() {
+            stats.by_hour[t.hour() as usize] += e.seconds_listened;
+        }
+
+        // same song from cache and from youtube should count once
+        let key = e
+            .video_id
+            .clone()
+            .unwrap_or_else(|| format!("{}\u{0}{}", e.title, e.artists.join(",")));
+        bump(&mut tracks, key, track_label(e), e.seconds_listened);
+
+        for artist in &e.artists {
+            bump(
+                &mut artists,
+                artist.to_lowercase(),
+                artist.clone(),
+                e.seconds_listened,
+            );
+        }
+        if !e.album.is_empty() {
+            let first_artist = e.artists.first().cloned().unwrap_or_default();
+            bump(
+                &mut albums,
+                format!("{}\u{0}{}", e.album.to_lowercase(), first_artist),
+                format!("{} - {}", e.album, first_artist),
+                e.seconds_listened,
+            );
+        }
+    }
+
+    stats.top_tracks = ranked(tracks, top);
+    stats.top_artists = ranked(artists, top);
+    stats.top_albums = ranked(albums, top);
+    stats
+}
+
+fn track_label(e: &HistoryEntry) -> String {
+    if e.artists.is_empty() {
+        e.title.clone()
+    } else {
+        format!("{} - {}", e.title, e.artists.join(", "))
+    }
+}
+
+fn bump(map: &mut HashMap<String, Ranked>, key: String, label: String, seconds: f64) {
+    let r = map.entry(key).or_insert_with(|| Ranked {
+        label,
+        ..Default::default()
+    });
+    r.plays += 1;
+    r.seconds += seconds;
+}
+
+fn ranked(map: HashMap<String, Ranked>, top: usize) -> Vec<Ranked> {
+    let mut list: Vec<Ranked> = map.into_values().collect();
+    list.sort_by(|a, b| {
+        b.plays
+            .cmp(&a.plays)
+            .then(b.seconds.total_cmp(&a.seconds))
+            .then(a.label.cmp(&b.label))
+    });
+    list.truncate(top);
+    list
+}
+
+pub fn format_duration(seconds: f64) -> String {
+    let s = seconds.max(0.0) as u64;
+    if s >= 3600 {
+        format!("{}h {:02}m", s / 3600, (s / 60) % 60)
+    } else {
+        format!("{}m {:02}s", s / 60, s % 60)
+    }
+}
+
+// ---- TERMINAL ----
+
+const BARS: [char; 9] = [' ', '▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+pub fn heatmap_line(by_hour: &[f64; 24]) -> String {
+    let max = by_hour.iter().cloned().fold(0.0, f64::max);
+    by_hour
+        .iter()
+        .map(|v| {
+            let level = if max > 0.0 {
+                ((v / max) * 8.0).ceil() as usize
+            } else {
+                0
+            };
+            let c = BARS[level.min(8)];
+            format!("{}{}", c, c)
+        })
+        .collect()
+}
+
+// plain lines without \r so both `whytui stats` and the tui can print them
+pub fn render_lines(stats: &Stats, title: &str) -> Vec<String> {
+    let mut lines = vec![
+        format!("--- {} ---", title.to_uppercase()).bold().underline().to_string(),
+        String::new(),
+    ];
+
+    if stats.plays == 0 {
+        lines.push("No plays in this window yet.".dimmed().to_string());
+        return lines;
+    }
+
+    lines.push(format!(
+        "{} plays | {} listened | {:.0}% skipped | {:.0}% lossless ({} of {}) | {} from cache",
+        stats.plays,
+        format_duration(stats.seconds).cyan(),
+        stats.skip_rate(),
+        stats.lossless_rate(),
+        stats.lossless,
+        stats.plays,
+        stats.from_cache
+    ));
+
+    for (name, list) in [
+        ("Top tracks", &stats.top_tracks),
+        ("Top artists", &stats.top_artists),
+        ("Top albums", &stats.top_albums),
+    ] {
+        if list.is_empty() {
+            continue;
+        }
+        lines.push(String::new());
+        lines.push(name.bold().to_string());
+        for (i, r) in list.iter().enumerate() {
+            lines.push(format!(
+                "{:>2}. {} [{} plays, {}]",
+                i + 1,
+                r.label,
+                r.plays,
+                format_duration(r.seconds).dimmed()
+            ));
+        }
+    }
+
+    lines.push(String::new());
+    lines.push("Listening by hour".bold().to_string());
+    lines.push(heatmap_line(&stats.by_hour).bright_blue().to_string());
+    lines.push("0     3     6     9     12    15    18    21".dimmed().to_string());
+    lines
+}
+
+// ---- REPORTS ----
+
+pub fn report_markdown(stats: &Stats, year: i32) -> String {
+    let mut out = format!("# whytui {} in review\n\n", year);
+
+    if stats.plays == 0 {
+        out.push_str("No plays recorded this year.\n");
+        return out;
+    }
+
+    out.push_str(&format!(
+        "- **{}** plays\n- **{}** listened\n- **{:.0}%** skipped\n- **{:.0}%** lossless ({} of {})\n- **{}** played from cache\n",
+        stats.plays,
+        format_duration(stats.seconds),
+        stats.skip_rate(),
+        stats.lossless_rate(),
+        stats.lossless,
+        stats.plays,
+        stats.from_cache
+    ));
+
+    for (name, list) in [
+        ("Top tracks", &stats.top_tracks),
+        ("Top artists", &stats.top_artists),
+        ("Top albums", &stats.top_albums),
+    ] {
+        if list.is_empty() {
+            continue;
+        }
+        out.push_str(&format!(
+            "\n## {}\n\n| # | Name | Plays | Time |\n|---|---|---|---|\n",
+            name
+        ));
+        for (i, r) in list.iter().enumerate() {
+            out.push_str(&format!(
+                "| {} | {} | {} | {} |\n",
+                i + 1,
+                r.label.replace('|', "\\|"),
+                r.plays,
+                format_duration(r.seconds)
+            ));
+        }
+    }
+
+    out.push_str("\n## Listening by hour\n\n```\n");
+    out.push_str(&heatmap_line(&stats.by_hour));
+    out.push_str("\n0     3     6     9     12    15    18    21\n```\n");
+    out
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// single file, inline css, no scripts or external assets
+pub fn report_html(stats: &Stats, year: i32) -> String {
+    let mut body = String::new();
+
+    if stats.plays == 0 {
+        body.push_str("<p>No plays recorded this year.</p>");
+    } else {
+        body.push_str(&format!(
+            "<div class=\"cards\">\
+             <div><b>{}</b>plays</div>\
+             <div><b>{}</b>listened</div>\
+             <div><b>{:.0}%</b>skipped</div>\
+             <div><b>{:.0}%</b>lossless</div>\
+             <div><b>{}</b>from cache</div></div>",
+            stats.plays,
+            format_duration(stats.seconds),
+            stats.skip_rate(),
+            stats.lossless_rate(),
+            stats.from_cache
+        ));
+
+        for (name, list) in [
+            ("Top tracks", &stats.top_tracks),
+            ("Top artists", &stats.top_artists),
+            ("Top albums", &stats.top_albums),
+        ] {
+            if list.is_empty() {
+                continue;
+            }
+            body.push_str(&format!("<h2>{}</h2><ol>", name));
+            for r in list {
+                body.push_str(&format!(
+                    "<li>{} <span>{} plays &middot; {}</span></li>",
+                    escape_html(&r.label),
+                    r.plays,
+                    format_duration(r.seconds)
+                ));
+            }
+            body.push_str("</ol>");
+        }
+
+        let max = stats.by_hour.iter().cloned().fold(0.0, f64::max);
+        body.push_str("<h2>Listening by hour</h2><table class=\"heat\"><tr>");
+        for v in &stats.by_hour {
+            let alpha = if max > 0.0 { v / max } else { 0.0 };
+            body.push_str(&format!(
+                "<td style=\"background:rgba(88,166,255,{:.2})\" title=\"{}\"></td>",
+                alpha,
+                format_duration(*v)
+            ));
+        }
+        body.push_str("</tr><tr>");
+        for h in 0..24 {
+            body.push_str(&format!("<th>{}</th>", h));
+        }
+        body.push_str("</tr></table>");
+    }
+
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>whytui {year} in review</title>
+<style>
+body {{ font-family: sans-serif; background: #0d1117; color: #e6edf3; max-width: 760px; margin: 2em auto; padding: 0 1em; }}
+h1 {{ font-size: 2em; }}
+h2 {{ border-bottom: 1px solid #30363d; padding-bottom: .2em; }}
+.cards {{ display: flex; flex-wrap: wrap; gap: 1em; }}
+.cards div {{ background: #161b22; border-radius: 8px; padding: 1em; min-width: 110px; }}
+.cards b {{ display: block; font-size: 1.5em; color: #58a6ff; }}
+li span {{ color: #8b949e; font-size: .9em; margin-left: .5em; }}
+.heat {{ border-spacing: 2px; width: 100%; }}
+.heat td {{ height: 2em; border-radius: 3px; border: 1px solid #30363d; }}
+.heat th {{ font-weight: normal; font-size: .7em; color: #8b949e; }}
+</style>
+</head>
+<body>
+<h1>whytui {year} in review</h1>
+{body}
+</body>
+</html>
+"#
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn play(timestamp: u64, title: &str, artist: &str, seconds: f64, skipped: bool) -> HistoryEntry {
+        HistoryEntry {
+            timestamp,
+            title: title.to_string(),
+            artists: vec![artist.to_string()],
+            album: String::new(),
+            duration: "3:00".to_string(),
+            video_id: Some(format!("id-{}", title)),
+            path: None,
+            thumbnail_url: None,
+            source: PlaySource::Youtube,
+            lossless: false,
+            seconds_listened: seconds,
+            skipped,
+        }
+    }
+
+    #[test]
+    fn parses_named_and_day_windows() {
+        assert_eq!(parse_window("week"), Ok(Window::Days(7)));
+        assert_eq!(parse_window(" Month "), Ok(Window::Days(30)));
+        assert_eq!(parse_window("all"), Ok(Window::All));
+        assert_eq!(parse_window("90d"), Ok(Window::Days(90)));
+        assert!(parse_window("0d").is_err());
+        assert!(parse_window("-3d").is_err());
+        assert!(parse_window("fortnight").is_err());
+    }
+
+    #[test]
+    fn huge_windows_reach_back_to_the_start() {
+        let w = parse_window("999999999999999d").unwrap();
+        assert_eq!(w.range().0, 0);
+    }
+
+    #[test]
+    fn year_range_covers_the_year() {
+        let (start, end) = year_range(2024).unwrap();
+        // 366 days, give or take a dst shift
+        assert!((end - start).abs_diff(366 * 86400) <= 3600);
+        assert!(year_range(i32::MAX).is_none());
+    }
+
+    #[test]
+    fn compute_counts_plays_in_range() {
+        let entries = vec![
+            play(100, "a", "x", 60.0, false),
+            play(200, "a", "x", 30.0, true),
+            play(300, "b", "y", 120.0, false),
+            play(5000, "c", "z", 10.0, false),
+        ];
+        let stats = compute(&entries, (0, 1000), 10);
+        assert_eq!(stats.plays, 3);
+        assert_eq!(stats.skipped, 1);
+        assert_eq!(stats.seconds, 210.0);
+        assert_eq!(stats.top_tracks[0].label, "a - x");
+        assert_eq!(stats.top_tracks[0].plays, 2);
+        assert_eq!(stats.top_artists.len(), 2);
+
+        let top1 = compute(&entries, (0, u64::MAX), 1);
+        assert_eq!(top1.top_tracks.len(), 1);
+    }
+
+    #[test]
+    fn formats_durations() {
+        assert_eq!(format_duration(59.0), "0m 59s");
+        assert_eq!(format_duration(3720.0), "1h 02m");
+        assert_eq!(format_duration(-5.0), "0m 00s");
+    }
+}
@@ -0,0 +1,151 @@
+use crate::{Track, config, player};
+use serde::{Deserialize, Serialize};
+use std::sync::RwLock;
+
+// -------------------------------------------------------------------
+// playback speed (pitch corrected by mpv) and a separate pitch shift.
+// speed is remembered per content type so a podcast at 1.5x doesn't
+// leave the next song at 1.5x; both live in config/tempo.json
+// -------------------------------------------------------------------
+
+pub const MIN_SPEED: f64 = 0.5;
+pub const MAX_SPEED: f64 = 3.0;
+pub const SPEED_STEP: f64 = 0.1;
+const MAX_PITCH: i64 = 12;
+
+const PITCH_LABEL: &str = "pitch";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Content {
+    Music,
+    // mixes, podcasts, audiobooks: anything over playback.long_track_minutes
+    Long,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+struct Saved {
+    music: f64,
+    long: f64,
+    // semitones
+    pitch: i64,
+}
+
+impl Default for Saved {
+    fn default() -> Self {
+        Self {
+            music: 1.0,
+            long: 1.0,
+            pitch: 0,
+        }
+    }
+}
+
+struct State {
+    saved: Saved,
+    content: Content,
+}
+
+static STATE: RwLock<State> = RwLock::new(State {
+    saved: Saved {
+        music: 1.0,
+        long: 1.0,
+        pitch: 0,
+    },
+    content: Content::Music,
+});
+
+fn saved_path() -> Option<std::path::PathBuf> {
+    Some(crate::settings::config_dir()?.join("tempo.json"))
+}
+
+fn save(saved: &Saved) {
+    if let (Some(path), Ok(data)) = (saved_path(), serde_json::to_string(saved)) {
+        let _ = std::fs::write(path, data);
+    }
+}
+
+pub fn content_of(track: &Track) -> Content {
+    let minutes = crate::ui_common::duration_to_seconds(&track.duration) / 60.0;
+    if minutes >= config().playback.long_track_minutes as f64 {
+        Content::Long
+    } else {
+        Content::Music
+    }
+}
+
+fn speed_of(saved: &Saved, content: Content) -> f64 {
+    match content {
+        Content::Music => saved.music,
+        Content::Long => saved.long,
+    }
+}
+
+// speed of whatever is playing now
+pub fn speed() -> f64 {
+    let st = STATE.read().unwrap();
+    speed_of(&st.saved, st.content)
+}
+
+pub fn pitch() -> i64 {
+    STATE.read().unwrap().saved.pitch
+}
+
+fn pitch_filter(semitones: i64) -> Option<String> {
+    (semitones != 0).then(|| {
+        format!(
+            "rubberband=pitch-scale={:.6}",
+            2f64.powf(semitones as f64 / 12.0)
+        )
+    })
+}
+
+// called once on startup
+pub fn init() {
+    let saved: Saved = saved_path()
+        .and_then(|p| std::fs::read_to_string(p).ok())
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default();
+    let mut st = STATE.write().unwrap();
+    st.saved = Saved {
+        music: saved.music.clamp(MIN_SPEED, MAX_SPEED),
+        long: saved.long.clamp(MIN_SPEED, MAX_SPEED),
+        pitch: saved.pitch.clamp(-MAX_PITCH, MAX_PITCH),
+    };
+    player::set_filter(PITCH_LABEL, pitch_filter(st.saved.pitch).as_deref());
+}
+
+// called before every track, switches to the speed of its content type
+pub fn prepare(track: &Track) {
+    let speed = {
+        let mut st = STATE.write().unwrap();
+        st.content = content_of(track);
+        speed_of(&st.saved, st.content)
+    };
+    player::set_speed(speed);
+}
+
+// sets the speed for the current content type, returns what it ended up as
+pub fn set_speed(speed: f64) -> f64 {
+    // keep it on the 0.05 grid so repeated steps don't drift
+    let speed = ((speed * 20.0).round() / 20.0).clamp(MIN_SPEED, MAX_SPEED);
+    let mut st = STATE.write().unwrap();
+    match st.content {
+        Content::Music => st.saved.music = speed,
+        Content::Long => st.saved.long = speed,
+    }
+    save(&st.saved);
+    drop(st);
+    player::set_speed(speed);
+    speed
+}
+
+pub fn set_pitch(semitones: i64) -> i64 {
+    let semitones = semitones.clamp(-MAX_PITCH, MAX_PITCH);
+    let mut st = STATE.write().unwrap();
+    st.saved.pitch = semitones;
+    save(&st.saved);
+    drop(st);
+    player::set_filter(PITCH_LABEL, pitch_filter(semitones).as_deref());
+    semitones
+}
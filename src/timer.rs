@@ -0,0 +1,366 @@
+use crate::{config, player};
+use chrono::{Local, NaiveTime, TimeZone};
+use std::sync::Mutex;
+use std::sync::atomic::Ordering;
+use std::sync::mpsc::Sender;
+use std::time::{Duration, Instant};
+
+// -------------------------------------------------------------------
+// sleep timer (after N minutes with a fade out, or after N songs) and a
+// wake-up alarm. a thread of its own watches the clock, turns the volume
+// and sends "timer:sleep" / "timer:alarm" down the remote channel when
+// one runs out, so the main loop stops or starts playback like for a key.
+// song counting happens in the main loop, only songs that end on their
+// own count
+// -------------------------------------------------------------------
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SleepSpec {
+    Off,
+    Minutes(u64),
+    // 1 = after the current song
+    Tracks(u32),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Sleep {
+    At(Instant),
+    Tracks(u32),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AlarmTarget {
+    Offline,
+    Playlist(String),
+}
+
+#[derive(Debug, Clone)]
+struct Alarm {
+    at: chrono::DateTime<Local>,
+    target: AlarmTarget,
+}
+
+struct Timers {
+    sleep: Option<Sleep>,
+    alarm: Option<Alarm>,
+    // the alarm that just went off, picked up by the main loop
+    fired: Option<AlarmTarget>,
+    // start of the wake-up volume ramp
+    ramp: Option<Instant>,
+}
+
+static TIMERS: Mutex<Timers> = Mutex::new(Timers {
+    sleep: None,
+    alarm: None,
+    fired: None,
+    ramp: None,
+});
+
+// ---- PARSING ----
+
+// "30", "30m", "track", "3 tracks", "off"
+pub fn parse_sleep(spec: &str) -> Result<SleepSpec, String> {
+    let words: Vec<&str> = spec.split_whitespace().collect();
+    match words.as_slice() {
+        ["off" | "cancel" | "0"] => Ok(SleepSpec::Off),
+        ["track" | "song" | "end"] => Ok(SleepSpec::Tracks(1)),
+        [n, "tracks" | "track" | "songs" | "song"] => match n.parse::<u32>() {
+            Ok(n) if n >= 1 => Ok(SleepSpec::Tracks(n)),
+            _ => Err(format!("'{}' is not a number of songs", n)),
+        },
+        [n] | [n, "min" | "minutes"] => match n.trim_end_matches('m').parse::<u64>() {
+            Ok(m) if (1..=24 * 60).contains(&m) => Ok(SleepSpec::Minutes(m)),
+            _ => Err(format!(
+                "sleep expects minutes (1-1440), 'track', 'N tracks' or 'off', got '{}'",
+                spec
+            )),
+        },
+        _ => Err(format!(
+            "sleep expects minutes, 'track', 'N tracks' or 'off', got '{}'",
+            spec
+        )),
+    }
+}
+
+// "7:30", "07:30"
+pub fn parse_alarm_time(s: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(s, "%H:%M")
+        .map_err(|_| format!("alarm expects a time like 07:30, got '{}'", s))
+}
+
+// "07:30", "07:30 offline", "07:30 <playlistId>", "off" -> None
+pub fn parse_alarm(spec: &str) -> Result<Option<(NaiveTime, AlarmTarget)>, String> {
+    let words: Vec<&str> = spec.split_whitespace().collect();
+    match words.as_slice() {
+        ["off" | "cancel"] => Ok(None),
+        [time] | [time, "offline" | "shuffle"] => {
+            Ok(Some((parse_alarm_time(time)?, AlarmTarget::Offline)))
+        }
+        [time, playlist] => Ok(Some((
+            parse_alarm_time(time)?,
+            AlarmTarget::Playlist(playlist.to_string()),
+        ))),
+        _ => Err(format!(
+            "alarm expects HH:MM [offline|<playlistId>] or 'off', got '{}'",
+            spec
+        )),
+    }
+}
+
+// next time the clock shows `time`, today or tomorrow
+fn next_occurrence(time: NaiveTime) -> chrono::DateTime<Local> {
+    let now = Local::now();
+    let today = now.date_naive().and_time(time);
+    let at = if today > now.naive_local() {
+        today
+    } else {
+        today + chrono::Duration::days(1)
+    };
+    // a dst gap has no such time, an hour later is close enough
+    let local = |t: chrono::NaiveDateTime| Local.from_local_datetime(&t).earliest();
+    local(at)
+        .or_else(|| local(at + chrono::Duration::hours(1)))
+        .unwrap_or_else(|| now + chrono::Duration::hours(24))
+}
+
+// ---- SETTING ----
+
+pub fn set_sleep(spec: SleepSpec) {
+    let mut t = TIMERS.lock().unwrap();
+    t.sleep = match spec {
+        SleepSpec::Off => None,
+        SleepSpec::Minutes(m) => Some(Sleep::At(Instant::now() + Duration::from_secs(m * 60))),
+        SleepSpec::Tracks(n) => Some(Sleep::Tracks(n)),
+    };
+    drop(t);
+    // a cancelled fade gives the volume back
+    player::set_volume(crate::VOLUME.load(Ordering::Relaxed));
+}
+
+// `z`: off -> each preset -> after this song -> off
+pub fn cycle_sleep() -> SleepSpec {
+    let presets = &config().timer.presets;
+    let current = TIMERS.lock().unwrap().sleep;
+    let next = match current {
+        None => presets
+            .first()
+            .map(|m| SleepSpec::Minutes(*m))
+            .unwrap_or(SleepSpec::Tracks(1)),
+        Some(Sleep::At(at)) => {
+            // the next preset longer than what's left (give or take a minute)
+            let left = at.saturating_duration_since(Instant::now()).as_secs();
+            presets
+                .iter()
+                .find(|m| **m * 60 > left + 60)
+                .map(|m| SleepSpec::Minutes(*m))
+                .unwrap_or(SleepSpec::Tracks(1))
+        }
+        Some(Sleep::Tracks(_)) => SleepSpec::Off,
+    };
+    set_sleep(next);
+    next
+}
+
+pub fn set_alarm(alarm: Option<(NaiveTime, AlarmTarget)>) {
+    TIMERS.lock().unwrap().alarm = alarm.map(|(time, target)| Alarm {
+        at: next_occurrence(time),
+        target,
+    });
+}
+
+// status line confirmation for a new sleep timer
+pub fn describe_sleep(spec: SleepSpec) -> String {
+    match spec {
+        SleepSpec::Off => "SLEEP TIMER OFF".to_string(),
+        SleepSpec::Minutes(m) => format!("SLEEP IN {} MIN", m),
+        SleepSpec::Tracks(1) => "STOP AFTER THIS SONG".to_string(),
+        SleepSpec::Tracks(n) => format!("STOP AFTER {} SONGS", n),
+    }
+}
+
+// ---- MAIN LOOP HOOKS ----
+
+// a song ended on its own, true when the sleep timer says stop here
+pub fn track_ended() -> bool {
+    let mut t = TIMERS.lock().unwrap();
+    match t.sleep {
+        Some(Sleep::Tracks(n)) if n <= 1 => {
+            t.sleep = None;
+            true
+        }
+        Some(Sleep::Tracks(n)) => {
+            t.sleep = Some(Sleep::Tracks(n - 1));
+            false
+        }
+        _ => false,
+    }
+}
+
+// nothing should be preloaded when playback stops after this song
+pub fn stops_after_current() -> bool {
+    matches!(TIMERS.lock().unwrap().sleep, Some(Sleep::Tracks(1)))
+}
+
+// what "timer:alarm" should start
+pub fn take_fired_alarm() -> Option<AlarmTarget> {
+    TIMERS.lock().unwrap().fired.take()
+}
+
+// the volume actually sent to the player while a fade or ramp is running
+pub fn scaled_volume(volume: i64) -> i64 {
+    let t = TIMERS.lock().unwrap();
+    let mut factor: f64 = 1.0;
+    let fade = config().timer.fade_out_secs as f64;
+    if let Some(Sleep::At(at)) = t.sleep
+        && fade > 0.0
+    {
+        let left = at.saturating_duration_since(Instant::now()).as_secs_f64();
+        factor = factor.min(left / fade);
+    }
+    let ramp = config().timer.wake_ramp_secs as f64;
+    if let Some(start) = t.ramp
+        && ramp > 0.0
+    {
+        factor = factor.min(start.elapsed().as_secs_f64() / ramp);
+    }
+    (volume as f64 * factor.clamp(0.0, 1.0)).round() as i64
+}
+
+// ---- STATUS ----
+
+fn mmss(d: Duration) -> String {
+    let secs = d.as_secs();
+    if secs >= 3600 {
+        format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+    } else {
+        format!("{}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+pub fn sleep_text() -> Option<String> {
+    match TIMERS.lock().unwrap().sleep? {
+        Sleep::At(at) => Some(format!(
+            "SLEEP IN {}",
+            mmss(at.saturating_duration_since(Instant::now()))
+        )),
+        Sleep::Tracks(1) => Some("STOP AFTER THIS SONG".to_string()),
+        Sleep::Tracks(n) => Some(format!("STOP AFTER {} SONGS", n)),
+    }
+}
+
+pub fn alarm_text() -> Option<String> {
+    let t = TIMERS.lock().unwrap();
+    let alarm = t.alarm.as_ref()?;
+    Some(format!("ALARM AT {}", alarm.at.format("%H:%M")))
+}
+
+// shown in the status line instead of the audio quality while set
+pub fn status_text() -> Option<String> {
+    sleep_text().or_else(alarm_text)
+}
+
+// ---- WATCHER ----
+
+pub fn spawn(tx: Sender<String>) {
+    std::thread::spawn(move || {
+        let mut shown: Option<String> = None;
+        let mut applied: Option<i64> = None;
+        loop {
+            std::thread::sleep(Duration::from_millis(250));
+
+            let (sleep_due, alarm_due, fading) = {
+                let mut t = TIMERS.lock().unwrap();
+                let sleep_due = matches!(t.sleep, Some(Sleep::At(at)) if Instant::now() >= at);
+                if sleep_due {
+                    t.sleep = None;
+                }
+                let alarm_due = t.alarm.as_ref().is_some_and(|a| Local::now() >= a.at);
+                if alarm_due {
+                    t.fired = t.alarm.take().map(|a| a.target);
+                    t.ramp = Some(Instant::now());
+                }
+                let ramp_done = t
+                    .ramp
+                    .is_some_and(|r| r.elapsed().as_secs() >= config().timer.wake_ramp_secs);
+                if ramp_done {
+                    t.ramp = None;
+                }
+                let fading = t.ramp.is_some() || matches!(t.sleep, Some(Sleep::At(_)));
+                (sleep_due, alarm_due, fading)
+            };
+
+            if sleep_due {
+                // the main loop stops playback and puts the volume back itself
+                applied = None;
+                let _ = tx.send("timer:sleep".to_string());
+            }
+            if alarm_due {
+                let _ = tx.send("timer:alarm".to_string());
+            }
+
+            // only talk to the player when the volume actually moves,
+            // None = full volume
+            let volume = crate::VOLUME.load(Ordering::Relaxed);
+            let want = Some(scaled_volume(volume)).filter(|v| fading && *v != volume);
+            if crate::IS_PLAYING.load(Ordering::SeqCst) && want != applied {
+                player::set_volume(volume);
+                applied = want;
+            }
+
+            let text = status_text();
+            if text != shown {
+                shown = text;
+                crate::ui_common::redraw_base_status();
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_sleep_specs() {
+        assert_eq!(parse_sleep("30"), Ok(SleepSpec::Minutes(30)));
+        assert_eq!(parse_sleep("45m"), Ok(SleepSpec::Minutes(45)));
+        assert_eq!(parse_sleep("90 min"), Ok(SleepSpec::Minutes(90)));
+        assert_eq!(parse_sleep("track"), Ok(SleepSpec::Tracks(1)));
+        assert_eq!(parse_sleep(" 3 songs "), Ok(SleepSpec::Tracks(3)));
+        assert_eq!(parse_sleep("off"), Ok(SleepSpec::Off));
+        assert_eq!(parse_sleep("0"), Ok(SleepSpec::Off));
+    }
+
+    #[test]
+    fn rejects_bad_sleep_specs() {
+        for spec in ["", "1441", "-5", "0 tracks", "soon", "3 days", "a b c"] {
+            assert!(parse_sleep(spec).is_err(), "{}", spec);
+        }
+    }
+
+    #[test]
+    fn parses_alarm_specs() {
+        let t = NaiveTime::from_hms_opt(7, 30, 0).unwrap();
+        assert_eq!(parse_alarm("07:30"), Ok(Some((t, AlarmTarget::Offline))));
+        assert_eq!(parse_alarm("7:30 shuffle"), Ok(Some((t, AlarmTarget::Offline))));
+        assert_eq!(
+            parse_alarm("07:30 PLxyz"),
+            Ok(Some((t, AlarmTarget::Playlist("PLxyz".to_string()))))
+        );
+        assert_eq!(parse_alarm("off"), Ok(None));
+        for spec in ["", "25:00", "7.30", "07:30 a b", "tomorrow"] {
+            assert!(parse_alarm(spec).is_err(), "{}", spec);
+        }
+    }
+
+    #[test]
+    fn alarm_goes_off_within_a_day() {
+        let now = Local::now();
+        let soon = (now + chrono::Duration::minutes(2)).time();
+        let at = next_occurrence(soon);
+        assert!(at > now && at - now <= chrono::Duration::hours(25));
+        // a minute ago is tomorrow
+        let past = next_occurrence((now - chrono::Duration::minutes(1)).time());
+        assert!(past - now >= chrono::Duration::hours(22));
+    }
+}
@@ -100,7 +100,8 @@ fn _draw_status_line(status: Option<String>) {
 
 // Public wrapper for temporary status updates
 pub fn set_status_line(status: Option<String>) {
-    *STATUS_TIMEOUT.write().unwrap() = Some(Instant::now() + Duration::from_millis(1000));
+    let timeout = Duration::from_millis(crate::config().ui.status_timeout_ms);
+    *STATUS_TIMEOUT.write().unwrap() = Some(Instant::now() + timeout);
     _draw_status_line(status);
 }
 
@@ -189,6 +190,39 @@ pub fn show_songs(list: &[SongDetails]) {
     stdout().flush().unwrap();
 }
 
+pub fn show_history(list: &[crate::history::HistoryEntry]) {
+    print!("\r\n");
+    for (i, e) in list.iter().enumerate() {
+        let when = chrono::DateTime::from_timestamp(e.timestamp as i64, 0)
+            .map(|d| {
+                d.with_timezone(&chrono::Local)
+                    .format("%d %b %H:%M")
+                    .to_string()
+            })
+            .unwrap_or_default();
+        let listened = e.seconds_listened as u64;
+        print!(
+            "\r\x1b[2K{}. {} {} [{}] [{:?}] [{}:{:02}{}]\r\n",
+            i + 1,
+            when.dimmed(),
+            e.title,
+            e.artists.join(", ").dimmed(),
+            e.source,
+            listened / 60,
+            listened % 60,
+            if e.skipped { " skipped" } else { "" },
+        );
+    }
+    print!(
+        "\r\x1b[2K{}",
+        format!("\n~ Select (1-{}): ", list.len())
+            .bright_blue()
+            .bold()
+            .blink()
+    );
+    stdout().flush().unwrap();
+}
+
 pub fn show_playlists(list: &[PlaylistDetails]) {
     print!(
         "\r\x1b[2K\n{}\r\n",
@@ -223,7 +257,7 @@ where
     let stop = Arc::new(AtomicBool::new(false));
 
     // fetch lyrics once per song
-    if !track.url.is_empty() {
+    if !track.url.is_empty() || track.video_id.is_some() {
         spawn_lyrics_fetcher(track.clone());
         // show the quality of the song always
         update_quality_status();
@@ -244,7 +278,9 @@ where
             check_status_timeout();
 
             // get current progress from playertitle
-            let (curr, player_tot) = player::get_time_info().unwrap_or((0.0, 0.0));
+            let time_info = player::get_time_info();
+            crate::history::note_position(time_info.map(|(pos, _)| pos));
+            let (curr, player_tot) = time_info.unwrap_or((0.0, 0.0));
             let lyrics = LYRICS.read().unwrap();
 
             //get lyric line
@@ -290,7 +326,10 @@ fn update_quality_status() {
     let is_lossless = crate::PLAYING_LOSSLESS.load(Ordering::SeqCst);
     let game_mode = crate::config().game_mode;
 
-    let text = if !game_mode {
+    // a running sleep timer / alarm takes the spot of the quality label
+    let text = if let Some(timer) = crate::timer::status_text() {
+        Some(format!("{:^31}", timer).dimmed().bold().to_string())
+    } else if !game_mode {
         if is_lossless {
             Some(
                 "     FLAC • LOSSLESS AUDIO     "
@@ -299,7 +338,10 @@ fn update_quality_status() {
                     .to_string(),
             )
         } else {
-            Some("     OPUS • STANDARD AUDIO     ".dimmed().to_string())
+            let label = crate::quality::playing()
+                .map(|info| info.label())
+                .unwrap_or_else(|| "STANDARD AUDIO".to_string());
+            Some(format!("{:^31}", label).dimmed().to_string())
         }
     } else {
         None
@@ -309,6 +351,13 @@ fn update_quality_status() {
     _draw_status_line(text);
 }
 
+// redraws the resting status line unless a message is still showing
+pub fn redraw_base_status() {
+    if STATUS_TIMEOUT.read().unwrap().is_none() {
+        update_quality_status();
+    }
+}
+
 fn check_status_timeout() {
     let timeout = STATUS_TIMEOUT
         .read()
@@ -324,9 +373,11 @@ fn check_status_timeout() {
 
 fn get_current_lyric_index(lyrics: &[LrcLine], curr_time: f64) -> usize {
     let offset = LYRIC_OFFSET.load(Ordering::Relaxed) as f64 / 1000.0;
+    // the look-ahead is wall clock time, in track time it grows with the speed
+    let ahead = 0.149 * crate::tempo::speed();
     lyrics
         .iter()
-        .position(|l| dur_to_secs(l.timestamp) > curr_time + 0.149 + offset)
+        .position(|l| dur_to_secs(l.timestamp) > curr_time + ahead + offset)
         .unwrap_or(lyrics.len())
         .saturating_sub(1)
 }
@@ -421,7 +472,7 @@ pub fn word_wrap_cjk(text: &str, max_width: usize) -> Vec<String> {
     lines
 }
 
-fn duration_to_seconds(duration: &str) -> f64 {
+pub fn duration_to_seconds(duration: &str) -> f64 {
     let parts: Vec<f64> = duration
         .split(':')
         .map(|p| p.parse::<f64>().unwrap_or(0.0))